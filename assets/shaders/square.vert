@@ -1,8 +1,6 @@
 #version 450 core
 
-layout (location = 0) in vec3 Position;
-layout (location = 1) in vec4 Color;
-layout (location = 2) in vec2 Texcoord;
+#pragma vertex_inputs(Vertex)
 
 out VS_OUTPUT {
     vec4 Color;
@@ -15,7 +13,7 @@ uniform mat4 projection;
 
 void main()
 {
-    gl_Position = projection * view * model * vec4(Position, 1.0);
-    vs_out.Color = Color;
-    vs_out.Texcoord = Texcoord;
+    gl_Position = projection * view * model * vec4(pos, 1.0);
+    vs_out.Color = color;
+    vs_out.Texcoord = texcoord;
 }
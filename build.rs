@@ -1,54 +1,156 @@
+use std::collections::HashMap;
 use std::env;
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
 use std::path::{Path, PathBuf};
 
 use walkdir::WalkDir;
 
+#[path = "src/resources/pack_format.rs"]
+mod pack_format;
+
+use pack_format::{Compression, Entry, IgnorePatterns, PackWriter, IGNORE_FILE};
+
 fn main() {
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
     let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let assets_dir = manifest_dir.join("assets");
 
-    let executable_path = find_parent_target_dir(&out_dir)
-        .expect("failed to find target dir")
-        .join(env::var("PROFILE").unwrap());
+    let ignore = IgnorePatterns::from_dir(&assets_dir).expect("failed to read the ignore file");
+    let assets = asset_files(&assets_dir, &ignore);
 
-    recursive_copy(
-        &manifest_dir.join("assets"),
-        &executable_path.join("assets"),
+    // the directory catches new and deleted files, the ignore file changes what is packed
+    println!("cargo:rerun-if-changed={}", assets_dir.display());
+    println!(
+        "cargo:rerun-if-changed={}",
+        assets_dir.join(IGNORE_FILE).display()
     );
+    for (_, path) in &assets {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+
+    match executable_dir(&out_dir) {
+        Some(dir) => write_pack(&assets, &dir.join("assets.pack")),
+        None => println!(
+            "cargo:warning=no asset pack written, {} is not in a cargo profile directory",
+            out_dir.display()
+        ),
+    }
+
+    if env::var_os("CARGO_FEATURE_EMBED_ASSETS").is_some() {
+        write_embedded_assets(&assets, &out_dir.join("embedded_assets.rs"));
+    }
 }
 
-// find the parent directory that is called target to copy files to
-fn find_parent_target_dir(mut child: &Path) -> Option<&Path> {
-    loop {
-        if child.ends_with("target") {
-            return Some(child);
+// OUT_DIR is <profile dir>/build/<package>-<hash>/out, wherever CARGO_TARGET_DIR or --target
+// put the profile dir, and the executable ends up in the profile dir
+fn executable_dir(out_dir: &Path) -> Option<&Path> {
+    let build_dir = out_dir.parent()?.parent()?;
+    if build_dir.file_name()? != "build" {
+        return None;
+    }
+    build_dir.parent()
+}
+
+// packs the assets next to the executable, where Resources::locate finds it
+//
+// entries of the previous pack are reused for files that did not change and the pack is only
+// written again if anything did
+//
+// a missing or unreadable pack is written from scratch, and reused entries are unpacked and
+// compared with the file, as the manifest of a damaged pack can still match
+fn write_pack(assets: &[(String, PathBuf)], to: &Path) {
+    let previous = read_previous_pack(to);
+
+    let mut writer = PackWriter::new();
+    let mut damaged = false;
+    for (name, path) in assets {
+        let data = fs::read(path).expect("failed to read asset");
+        let hash = pack_format::hash(&data);
+        match previous
+            .as_ref()
+            .and_then(|(entries, _)| entries.get(name))
+            .filter(|(entry, _)| entry.hash == hash)
+        {
+            Some((entry, packed))
+                if pack_format::unpack(entry, packed).ok().as_deref() == Some(&data[..]) =>
+            {
+                writer.add_packed(entry.clone(), packed.clone());
+            }
+            reused => {
+                damaged |= reused.is_some();
+                writer
+                    .add(name, &data, Compression::Deflate)
+                    .expect("failed to compress asset");
+            }
         }
-        child = match child.parent() {
-            Some(path) => path,
-            None => break,
+    }
+
+    if let Some((_, manifest)) = &previous {
+        if !damaged && *manifest == writer.manifest() {
+            return;
         }
     }
-    None
+
+    let out = File::create(to).expect("failed to create asset pack");
+    writer
+        .write(&mut BufWriter::new(out))
+        .expect("failed to write asset pack");
 }
 
-fn recursive_copy(from: &Path, to: &Path) {
-    let from_path: PathBuf = from.into();
-    let to_path: PathBuf = to.into();
-    for entry in WalkDir::new(from_path.clone()) {
-        let entry = entry.unwrap();
+type PackedEntries = HashMap<String, (Entry, Vec<u8>)>;
 
-        if let Ok(rel_path) = entry.path().strip_prefix(&from_path) {
-            let target_path = to_path.join(rel_path);
+// the entries of the pack at path with their packed data, None if there is no readable pack
+fn read_previous_pack(path: &Path) -> Option<(PackedEntries, pack_format::Manifest)> {
+    let bytes = fs::read(path).ok()?;
+    let mut reader = io::Cursor::new(&bytes[..]);
+    let (manifest, data_start) = pack_format::read_header(&mut reader).ok()?;
 
-            if entry.file_type().is_dir() {
-                fs::DirBuilder::new()
-                    .recursive(true)
-                    .create(target_path)
-                    .expect("failed to create target dir");
-            } else {
-                fs::copy(entry.path(), &target_path).expect("failed to copy");
-            }
+    let mut entries = HashMap::new();
+    for entry in &manifest.entries {
+        let start = (data_start + entry.offset) as usize;
+        let packed = bytes.get(start..start + entry.packed_size as usize)?;
+        entries.insert(entry.name.clone(), (entry.clone(), packed.to_vec()));
+    }
+    Some((entries, manifest))
+}
+
+// every file below dir that is not ignored with its resource name, sorted by name
+fn asset_files(dir: &Path, ignore: &IgnorePatterns) -> Vec<(String, PathBuf)> {
+    let mut assets = Vec::new();
+    for entry in WalkDir::new(dir) {
+        let entry = entry.unwrap();
+        if !entry.file_type().is_file() {
+            continue;
         }
+
+        let rel_path = entry.path().strip_prefix(dir).unwrap();
+        let name = rel_path
+            .components()
+            .map(|part| part.as_os_str().to_str().expect("asset path is not UTF-8"))
+            .collect::<Vec<_>>()
+            .join("/");
+        if !ignore.is_ignored(&name) {
+            assets.push((name, entry.path().to_owned()));
+        }
+    }
+    assets.sort();
+    assets
+}
+
+// generates a table of the assets sorted by resource name, included by
+// resources::EmbeddedSource
+//
+// only written when it changed, so the crate is not rebuilt for nothing
+fn write_embedded_assets(assets: &[(String, PathBuf)], to: &Path) {
+    let mut table = String::from("pub static ASSETS: &[(&str, &[u8])] = &[\n");
+    for (name, path) in assets {
+        let path = path.to_str().expect("asset path is not UTF-8");
+        table.push_str(&format!("    ({:?}, include_bytes!({:?})),\n", name, path));
+    }
+    table.push_str("];\n");
+
+    if fs::read_to_string(to).ok().as_deref() != Some(&table[..]) {
+        fs::write(to, table).expect("failed to write embedded assets");
     }
 }
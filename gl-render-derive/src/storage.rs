@@ -0,0 +1,209 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{self, spanned::Spanned};
+
+use crate::{check_repr_c, named_fields};
+
+struct StorageField<'a> {
+    field: &'a syn::Field,
+    // #[padding] fields only move the following fields to their std430 offsets
+    padding: bool,
+    // element type of a trailing [T; 0], the runtime-sized array of the block
+    runtime_element: Option<&'a syn::Type>,
+}
+
+pub fn impl_storage_block(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    check_repr_c(ast, "StorageBlock")?;
+    let fields = parse_storage_fields(&ast.data, ast)?;
+    let members: Vec<&StorageField> = fields.iter().filter(|field| !field.padding).collect();
+
+    let layout_checks = generate_layout_checks(&members);
+    let descs = members.iter().map(|field| generate_member_desc(field));
+
+    let align = members.iter().fold(quote! { 1 }, |align, field| {
+        let field_ty = &field.field.ty;
+        quote! {
+            crate::gl_render::std430::max_align(
+                #align,
+                <#field_ty as crate::gl_render::std430::Std430>::ALIGN,
+            )
+        }
+    });
+    let size = match members
+        .iter()
+        .rev()
+        .find(|field| field.runtime_element.is_none())
+    {
+        Some(last) => {
+            let field_ident = last.field.ident.as_ref().unwrap();
+            let field_ty = &last.field.ty;
+            quote! {
+                ::std::mem::offset_of!(Self, #field_ident)
+                    + <#field_ty as crate::gl_render::std430::Std430>::SIZE
+            }
+        }
+        None => quote! { 0 },
+    };
+    let (element, element_offset) = match members.last().and_then(|field| {
+        field
+            .runtime_element
+            .map(|element| (element, field.field.ident.as_ref().unwrap()))
+    }) {
+        Some((element, field_ident)) => (
+            quote! { #element },
+            quote! { ::std::mem::offset_of!(Self, #field_ident) },
+        ),
+        None => (quote! { () }, quote! { ::std::mem::size_of::<Self>() }),
+    };
+
+    let gen = quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #[allow(unused_mut, unused_assignments)]
+            const STD430_LAYOUT_CHECK: () = {
+                let mut end: usize = 0;
+                #(#layout_checks)*
+            };
+        }
+
+        impl #impl_generics crate::gl_render::std430::Std430 for #ident #ty_generics
+            #where_clause
+        {
+            #[allow(clippy::let_unit_value)]
+            const ALIGN: usize = {
+                let () = Self::STD430_LAYOUT_CHECK;
+                #align
+            };
+            const SIZE: usize = crate::gl_render::std430::align_up(#size, Self::ALIGN);
+            const GL_TYPE: u32 = 0;
+            const MEMBERS: &'static [crate::gl_render::std430::StorageMember] = &[
+                #(#descs),*
+            ];
+        }
+
+        impl #impl_generics crate::gl_render::std430::StorageBlock for #ident #ty_generics
+            #where_clause
+        {
+            type Element = #element;
+            const ELEMENT_OFFSET: usize = #element_offset;
+        }
+    };
+    Ok(gen)
+}
+
+fn parse_storage_fields<'a>(
+    data: &'a syn::Data,
+    ast: &syn::DeriveInput,
+) -> syn::Result<Vec<StorageField<'a>>> {
+    let fields = named_fields(data, ast, "StorageBlock")?;
+
+    let mut errors: Option<syn::Error> = None;
+    let mut push_error = |err: syn::Error| match errors {
+        Some(ref mut errors) => errors.combine(err),
+        None => errors = Some(err),
+    };
+
+    let mut storage_fields = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let padding = match field.attrs.iter().find(|a| a.path.is_ident("padding")) {
+            Some(attr) => match attr.parse_meta() {
+                Ok(syn::Meta::Path(_)) => true,
+                _ => {
+                    push_error(syn::Error::new(
+                        attr.span(),
+                        "expected a plain #[padding] attribute",
+                    ));
+                    continue;
+                }
+            },
+            None => false,
+        };
+
+        let runtime_element = if padding {
+            None
+        } else {
+            runtime_array_element(&field.ty)
+        };
+        if runtime_element.is_some() && index + 1 != fields.len() {
+            push_error(syn::Error::new(
+                field.ty.span(),
+                "only the last field of a storage block can be a runtime-sized array",
+            ));
+            continue;
+        }
+
+        storage_fields.push(StorageField {
+            field,
+            padding,
+            runtime_element,
+        });
+    }
+
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(storage_fields),
+    }
+}
+
+// `T name[];` is declared as `name: [T; 0]`
+fn runtime_array_element(ty: &syn::Type) -> Option<&syn::Type> {
+    match ty {
+        syn::Type::Array(syn::TypeArray {
+            elem,
+            len:
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(len),
+                    ..
+                }),
+            ..
+        }) if len.base10_digits() == "0" => Some(elem),
+        _ => None,
+    }
+}
+
+// the Rust offset of every member has to match the offset std430 gives it, this is checked
+// by const evaluation whenever the layout of the struct is used
+fn generate_layout_checks(members: &[&StorageField]) -> Vec<TokenStream> {
+    members
+        .iter()
+        .map(|field| {
+            let field_ident = field.field.ident.as_ref().unwrap();
+            let field_ty = &field.field.ty;
+            let message = format!(
+                "field `{}` is not at its std430 offset, add #[padding] fields before it",
+                field_ident
+            );
+            quote_spanned! {field_ty.span()=>
+                let offset = crate::gl_render::std430::align_up(
+                    end,
+                    <#field_ty as crate::gl_render::std430::Std430>::ALIGN,
+                );
+                if offset != ::std::mem::offset_of!(Self, #field_ident) {
+                    panic!(#message);
+                }
+                end = offset + <#field_ty as crate::gl_render::std430::Std430>::SIZE;
+            }
+        })
+        .collect()
+}
+
+fn generate_member_desc(field: &StorageField) -> TokenStream {
+    let field_ident = field.field.ident.as_ref().unwrap();
+    let field_name = field_ident.to_string();
+    let field_ty = &field.field.ty;
+    let runtime_sized = field.runtime_element.is_some();
+
+    quote_spanned! {field_ty.span()=>
+        crate::gl_render::std430::StorageMember {
+            name: #field_name,
+            offset: ::std::mem::offset_of!(Self, #field_ident),
+            gl_type: <#field_ty as crate::gl_render::std430::Std430>::GL_TYPE,
+            array_len: <#field_ty as crate::gl_render::std430::Std430>::ARRAY_LEN,
+            array_stride: <#field_ty as crate::gl_render::std430::Std430>::ARRAY_STRIDE,
+            runtime_sized: #runtime_sized,
+            members: <#field_ty as crate::gl_render::std430::Std430>::MEMBERS,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{self, spanned::Spanned};
+
+use crate::named_fields;
+
+pub fn impl_program_uniforms(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let fields = named_fields(&ast.data, ast, "ProgramUniforms")?;
+
+    let descs = fields.iter().map(|field| {
+        let field_name = field.ident.as_ref().unwrap().to_string();
+        let field_ty = &field.ty;
+        quote_spanned! {field_ty.span()=>
+            crate::gl_render::uniform::UniformDesc {
+                name: #field_name,
+                gl_type: <#field_ty as crate::gl_render::uniform::Uniform>::GL_TYPE,
+            }
+        }
+    });
+
+    let uploads = fields.iter().enumerate().map(|(index, field)| {
+        let field_ident = field.ident.as_ref().unwrap();
+        let field_ty = &field.ty;
+        quote_spanned! {field_ty.span()=>
+            <#field_ty as crate::gl_render::uniform::Uniform>::set(
+                program,
+                locations[#index],
+                &self.#field_ident,
+            );
+        }
+    });
+
+    let gen = quote! {
+        impl #impl_generics crate::gl_render::uniform::ProgramUniforms for #ident #ty_generics
+            #where_clause
+        {
+            const UNIFORMS: &'static [crate::gl_render::uniform::UniformDesc] = &[
+                #(#descs),*
+            ];
+
+            fn upload(&self, program: &crate::gl_render::Program, locations: &[gl::types::GLint]) {
+                #(#uploads)*
+            }
+        }
+    };
+    Ok(gen)
+}
@@ -0,0 +1,228 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{self, spanned::Spanned};
+
+use crate::{check_repr_c, named_fields};
+
+struct VertexField<'a> {
+    field: &'a syn::Field,
+    // explicit #[location = N], otherwise the next free location after the previous field
+    location: Option<usize>,
+    location_span: proc_macro2::Span,
+}
+
+pub fn impl_vertex_attrib_pointers(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    check_repr_c(ast, "VertexAttribPointers")?;
+    let fields = parse_vertex_fields(&ast.data, ast)?;
+
+    let descs = generate_attribute_descs(&fields);
+    let field_count = fields.len();
+    let names_len: usize = fields
+        .iter()
+        .map(|field| field.field.ident.as_ref().unwrap().to_string().len())
+        .sum();
+    let overlap_checks = generate_location_overlap_checks(&fields);
+    let calls = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| generate_struct_field_vertex_attrib_format_call(index, field));
+
+    let gen = quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            const UNCHECKED_LAYOUT: &'static [crate::gl_render::data::VertexAttributeDesc] = &[
+                #(#descs),*
+            ];
+
+            // GLSL_INPUTS and vertex_format are built from LAYOUT, so all of them fail to
+            // compile when locations overlap
+            #[allow(clippy::let_unit_value)]
+            pub const LAYOUT: &'static [crate::gl_render::data::VertexAttributeDesc] = {
+                let () = Self::LOCATION_OVERLAP_CHECK;
+                Self::UNCHECKED_LAYOUT
+            };
+
+            const GLSL_INPUTS_BUFFER: (
+                [u8; #field_count * crate::gl_render::data::GLSL_INPUT_LINE_OVERHEAD + #names_len],
+                usize,
+            ) = crate::gl_render::data::glsl_inputs(Self::LAYOUT);
+
+            // `layout (location = N) in <type> <field>;` declarations matching LAYOUT
+            pub const GLSL_INPUTS: &'static str = crate::gl_render::data::glsl_inputs_str(
+                &Self::GLSL_INPUTS_BUFFER.0,
+                Self::GLSL_INPUTS_BUFFER.1,
+            );
+
+            const LOCATION_OVERLAP_CHECK: () = {
+                #(#overlap_checks)*
+            };
+
+            pub fn vertex_format(vao: &crate::gl_render::buffer::VertexArray, binding: usize) {
+                #(#calls)*
+            }
+        }
+    };
+    Ok(gen)
+}
+
+fn parse_vertex_fields<'a>(
+    data: &'a syn::Data,
+    ast: &syn::DeriveInput,
+) -> syn::Result<Vec<VertexField<'a>>> {
+    let fields = named_fields(data, ast, "VertexAttribPointers")?;
+
+    let mut errors: Option<syn::Error> = None;
+    let mut push_error = |err: syn::Error| match errors {
+        Some(ref mut errors) => errors.combine(err),
+        None => errors = Some(err),
+    };
+
+    let mut vertex_fields: Vec<VertexField> = Vec::new();
+    for field in fields {
+        let vertex_field = match parse_vertex_field(field) {
+            Ok(vertex_field) => vertex_field,
+            Err(err) => {
+                push_error(err);
+                continue;
+            }
+        };
+
+        let duplicate = vertex_fields.iter().find(|other| {
+            vertex_field.location.is_some() && other.location == vertex_field.location
+        });
+        if let Some(other) = duplicate {
+            push_error(syn::Error::new(
+                vertex_field.location_span,
+                format!(
+                    "location {} is already used by field `{}`",
+                    vertex_field.location.unwrap(),
+                    other.field.ident.as_ref().unwrap()
+                ),
+            ));
+            continue;
+        }
+
+        vertex_fields.push(vertex_field);
+    }
+
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(vertex_fields),
+    }
+}
+
+fn parse_vertex_field(field: &syn::Field) -> syn::Result<VertexField<'_>> {
+    let location_attr = match field.attrs.iter().find(|a| a.path.is_ident("location")) {
+        Some(location_attr) => location_attr,
+        None => {
+            return Ok(VertexField {
+                field,
+                location: None,
+                location_span: field.ident.span(),
+            })
+        }
+    };
+
+    let location_lit = match location_attr.parse_meta()? {
+        syn::Meta::NameValue(val) => val.lit,
+        other => {
+            return Err(syn::Error::new(
+                other.span(),
+                "expected a name value attribute like #[location = 0]",
+            ))
+        }
+    };
+
+    let location_span = location_lit.span();
+    let location = match location_lit {
+        syn::Lit::Int(digit) => digit.base10_parse::<usize>()?,
+        other => {
+            return Err(syn::Error::new(
+                other.span(),
+                "location must be an unsigned integer literal",
+            ))
+        }
+    };
+
+    Ok(VertexField {
+        field,
+        location: Some(location),
+        location_span,
+    })
+}
+
+fn generate_attribute_descs(fields: &[VertexField]) -> Vec<TokenStream> {
+    let mut descs = Vec::new();
+    let mut next_location = quote! { 0 };
+    for field in fields {
+        let field_ident = field.field.ident.as_ref().unwrap();
+        let field_name = field_ident.to_string();
+        let field_ty = &field.field.ty;
+        let location = match field.location {
+            Some(location) => quote! { #location },
+            None => next_location,
+        };
+
+        descs.push(quote_spanned! {field.location_span=>
+            crate::gl_render::data::VertexAttributeDesc {
+                name: #field_name,
+                location: #location,
+                locations: <#field_ty as crate::gl_render::data::VertexAttribute>::LOCATIONS,
+                components: <#field_ty as crate::gl_render::data::VertexAttribute>::COMPONENTS,
+                gl_type: <#field_ty as crate::gl_render::data::VertexAttribute>::GL_TYPE,
+                normalized: <#field_ty as crate::gl_render::data::VertexAttribute>::NORMALIZED,
+                path: <#field_ty as crate::gl_render::data::VertexAttribute>::PATH,
+                offset: ::std::mem::offset_of!(Self, #field_ident),
+            }
+        });
+
+        next_location = quote! {
+            (#location) + <#field_ty as crate::gl_render::data::VertexAttribute>::LOCATIONS
+        };
+    }
+    descs
+}
+
+// matrices take several consecutive locations, so overlaps can only be checked once the
+// field types are known, this is done by const evaluation when LAYOUT is used
+fn generate_location_overlap_checks(fields: &[VertexField]) -> Vec<TokenStream> {
+    let mut checks = Vec::new();
+    for (a_index, a) in fields.iter().enumerate() {
+        for (b_index, b) in fields.iter().enumerate().skip(a_index + 1) {
+            let message = format!(
+                "attribute locations of fields `{}` and `{}` overlap",
+                a.field.ident.as_ref().unwrap(),
+                b.field.ident.as_ref().unwrap()
+            );
+            checks.push(quote_spanned! {b.location_span=>
+                let a = &Self::UNCHECKED_LAYOUT[#a_index];
+                let b = &Self::UNCHECKED_LAYOUT[#b_index];
+                if a.location < b.location + b.locations && b.location < a.location + a.locations {
+                    panic!(#message);
+                }
+            });
+        }
+    }
+    checks
+}
+
+fn generate_struct_field_vertex_attrib_format_call(
+    index: usize,
+    field: &VertexField,
+) -> TokenStream {
+    let field_ty = &field.field.ty;
+
+    quote_spanned! {field_ty.span()=>
+        let desc = &Self::LAYOUT[#index];
+        <#field_ty as crate::gl_render::data::VertexAttribute>::vertex_attrib_format(
+            vao,
+            desc.location,
+            desc.offset,
+        );
+        for location in desc.location..desc.location + desc.locations {
+            vao.attrib_binding(location, binding);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+// minimal stand-in for the gl_render module the derive expands against
+
+pub mod buffer {
+    pub struct VertexArray;
+
+    impl VertexArray {
+        pub fn attrib_binding(&self, _location: usize, _binding: usize) {}
+    }
+}
+
+pub mod data {
+    use super::buffer::VertexArray;
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum AttributePath {
+        Float,
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct VertexAttributeDesc {
+        pub name: &'static str,
+        pub location: usize,
+        pub locations: usize,
+        pub components: usize,
+        pub gl_type: u32,
+        pub normalized: bool,
+        pub path: AttributePath,
+        pub offset: usize,
+    }
+
+    pub const GLSL_INPUT_LINE_OVERHEAD: usize = 64;
+
+    pub const fn glsl_inputs<const N: usize>(_layout: &[VertexAttributeDesc]) -> ([u8; N], usize) {
+        ([0; N], 0)
+    }
+
+    pub const fn glsl_inputs_str(_buffer: &'static [u8], _len: usize) -> &'static str {
+        ""
+    }
+
+    pub trait VertexAttribute {
+        const COMPONENTS: usize;
+        const GL_TYPE: u32 = 0x1406;
+        const NORMALIZED: bool = false;
+        const PATH: AttributePath = AttributePath::Float;
+        const LOCATIONS: usize = 1;
+
+        fn vertex_attrib_format(_vao: &VertexArray, _location: usize, _offset: usize) {}
+    }
+
+    impl VertexAttribute for [f32; 3] {
+        const COMPONENTS: usize = 3;
+    }
+
+    impl VertexAttribute for [f32; 16] {
+        const COMPONENTS: usize = 4;
+        const LOCATIONS: usize = 4;
+    }
+}
+
+pub struct Program;
+
+pub mod uniform {
+    use super::Program;
+
+    pub trait Uniform {
+        const GL_TYPE: u32;
+
+        fn set(_program: &Program, _location: i32, _value: &Self) {}
+    }
+
+    impl Uniform for f32 {
+        const GL_TYPE: u32 = 0x1406;
+    }
+
+    impl Uniform for [f32; 16] {
+        const GL_TYPE: u32 = 0x8b5c;
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct UniformDesc {
+        pub name: &'static str,
+        pub gl_type: u32,
+    }
+
+    pub trait ProgramUniforms {
+        const UNIFORMS: &'static [UniformDesc];
+
+        fn upload(&self, program: &Program, locations: &[i32]);
+    }
+}
+
+// the real layout rules, so the derive is checked against the impls the crate uses
+#[path = "../../../src/gl_render/std430.rs"]
+pub mod std430;
@@ -0,0 +1,12 @@
+use gl_render_derive::VertexAttribPointers;
+
+#[derive(VertexAttribPointers)]
+#[repr(C)]
+struct Vertex {
+    #[location = "zero"]
+    pos: [f32; 3],
+    #[location(1)]
+    color: [f32; 3],
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use gl_render_derive::VertexAttribPointers;
+
+#[derive(VertexAttribPointers)]
+#[repr(C)]
+struct Vertex {
+    #[location = 0]
+    pos: [f32; 3],
+    #[location = 0]
+    color: [f32; 3],
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use gl_render_derive::VertexAttribPointers;
+
+#[derive(VertexAttribPointers)]
+struct Vertex {
+    #[location = 0]
+    pos: [f32; 3],
+}
+
+fn main() {}
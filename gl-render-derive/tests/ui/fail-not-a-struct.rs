@@ -0,0 +1,9 @@
+use gl_render_derive::VertexAttribPointers;
+
+#[derive(VertexAttribPointers)]
+#[repr(C)]
+enum Vertex {
+    Position([f32; 3]),
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+#[path = "../support/gl_render.rs"]
+mod gl_render;
+
+use gl_render_derive::VertexAttribPointers;
+
+#[derive(VertexAttribPointers)]
+#[repr(C)]
+struct Vertex {
+    #[location = 1]
+    color: [f32; 3],
+    transform: [f32; 16],
+    #[location = 4]
+    normal: [f32; 3],
+}
+
+fn main() {
+    Vertex::vertex_format(&gl_render::buffer::VertexArray, 0);
+}
@@ -0,0 +1,17 @@
+#[path = "../support/gl_render.rs"]
+mod gl_render;
+
+use gl_render_derive::VertexAttribPointers;
+
+#[derive(VertexAttribPointers)]
+#[repr(C)]
+struct Vertex {
+    #[location = 0]
+    transform: [f32; 16],
+    #[location = 2]
+    color: [f32; 3],
+}
+
+fn main() {
+    Vertex::vertex_format(&gl_render::buffer::VertexArray, 0);
+}
@@ -0,0 +1,16 @@
+#[path = "../support/gl_render.rs"]
+mod gl_render;
+
+use gl_render::std430::Std430;
+use gl_render_derive::StorageBlock;
+use nalgebra_glm::Vec3;
+
+#[derive(Copy, Clone, StorageBlock)]
+#[repr(C)]
+struct Block {
+    positions: [Vec3; 4],
+}
+
+fn main() {
+    let _ = Block::ALIGN;
+}
@@ -0,0 +1,17 @@
+#[path = "../support/gl_render.rs"]
+mod gl_render;
+
+use gl_render::std430::Std430;
+use gl_render_derive::StorageBlock;
+use nalgebra_glm::Vec4;
+
+#[derive(Copy, Clone, StorageBlock)]
+#[repr(C)]
+struct Block {
+    time: f32,
+    color: Vec4,
+}
+
+fn main() {
+    let _ = Block::ALIGN;
+}
@@ -0,0 +1,13 @@
+#[path = "../support/gl_render.rs"]
+mod gl_render;
+
+use gl_render_derive::StorageBlock;
+
+#[derive(Copy, Clone, StorageBlock)]
+#[repr(C)]
+struct Block {
+    values: [f32; 0],
+    count: u32,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use gl_render_derive::ProgramUniforms;
+
+#[derive(ProgramUniforms)]
+struct Uniforms(f32, f32);
+
+fn main() {}
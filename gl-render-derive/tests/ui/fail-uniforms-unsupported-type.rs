@@ -0,0 +1,18 @@
+#[path = "../support/gl_render.rs"]
+mod gl_render;
+
+mod gl {
+    pub mod types {
+        pub type GLint = i32;
+    }
+}
+
+use gl_render_derive::ProgramUniforms;
+
+#[derive(ProgramUniforms)]
+struct Uniforms {
+    time: f32,
+    name: String,
+}
+
+fn main() {}
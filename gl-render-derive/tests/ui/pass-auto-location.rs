@@ -0,0 +1,26 @@
+#[path = "../support/gl_render.rs"]
+mod gl_render;
+
+use gl_render_derive::VertexAttribPointers;
+
+#[derive(Copy, Clone, VertexAttribPointers)]
+#[repr(C)]
+struct Vertex {
+    pos: [f32; 3],
+    transform: [f32; 16],
+    color: [f32; 3],
+    #[location = 8]
+    normal: [f32; 3],
+    tangent: [f32; 3],
+}
+
+fn main() {
+    let locations: Vec<usize> = Vertex::LAYOUT.iter().map(|desc| desc.location).collect();
+    assert_eq!(locations, [0, 1, 5, 8, 9]);
+    let offsets: Vec<usize> = Vertex::LAYOUT.iter().map(|desc| desc.offset).collect();
+    assert_eq!(offsets, [0, 12, 76, 88, 100]);
+    assert_eq!(Vertex::LAYOUT[1].name, "transform");
+    assert_eq!(Vertex::LAYOUT[1].locations, 4);
+
+    Vertex::vertex_format(&gl_render::buffer::VertexArray, 0);
+}
@@ -0,0 +1,19 @@
+#[path = "../support/gl_render.rs"]
+mod gl_render;
+
+use gl_render_derive::VertexAttribPointers;
+
+#[derive(Copy, Clone, VertexAttribPointers)]
+#[repr(C, packed)]
+struct Vertex {
+    #[location = 0]
+    pos: [f32; 3],
+    #[location = 1]
+    transform: [f32; 16],
+    #[location = 5]
+    color: [f32; 3],
+}
+
+fn main() {
+    Vertex::vertex_format(&gl_render::buffer::VertexArray, 0);
+}
@@ -0,0 +1,21 @@
+#[path = "../support/gl_render.rs"]
+mod gl_render;
+
+use gl_render::data::VertexAttribute;
+use gl_render_derive::VertexAttribPointers;
+
+#[derive(Copy, Clone, VertexAttribPointers)]
+#[repr(C)]
+struct Vertex<T: VertexAttribute + Copy, U>
+where
+    U: VertexAttribute + Copy,
+{
+    #[location = 0]
+    pos: T,
+    #[location = 1]
+    normal: U,
+}
+
+fn main() {
+    Vertex::<[f32; 3], [f32; 3]>::vertex_format(&gl_render::buffer::VertexArray, 0);
+}
@@ -0,0 +1,47 @@
+#[path = "../support/gl_render.rs"]
+mod gl_render;
+
+use gl_render::std430::{Std430, StorageBlock};
+use gl_render_derive::StorageBlock;
+use nalgebra_glm::{Vec3, Vec4};
+
+#[derive(Copy, Clone, StorageBlock)]
+#[repr(C)]
+struct Particle {
+    position: Vec3,
+    age: f32,
+    velocity: Vec3,
+    #[padding]
+    _pad: f32,
+}
+
+#[derive(Copy, Clone, StorageBlock)]
+#[repr(C)]
+struct Particles {
+    count: u32,
+    #[padding]
+    _pad: [u32; 3],
+    gravity: Vec4,
+    weights: [f32; 2],
+    #[padding]
+    _pad2: [f32; 2],
+    particles: [Particle; 0],
+}
+
+fn main() {
+    assert_eq!(Particle::ALIGN, 16);
+    assert_eq!(Particle::SIZE, 32);
+    assert_eq!(Particle::MEMBERS.len(), 3);
+    assert_eq!(Particle::MEMBERS[2].offset, 16);
+
+    assert_eq!(Particles::ALIGN, 16);
+    let offsets: Vec<usize> = Particles::MEMBERS.iter().map(|m| m.offset).collect();
+    assert_eq!(offsets, [0, 16, 32, 48]);
+    assert_eq!(Particles::MEMBERS[2].array_len, 2);
+    assert_eq!(Particles::MEMBERS[2].array_stride, 4);
+    assert!(Particles::MEMBERS[3].runtime_sized);
+    assert_eq!(Particles::MEMBERS[3].array_stride, 32);
+    assert_eq!(Particles::MEMBERS[3].members, Particle::MEMBERS);
+    assert_eq!(<Particles as StorageBlock>::ELEMENT_OFFSET, 48);
+    assert_eq!(<Particle as StorageBlock>::ELEMENT_OFFSET, 32);
+}
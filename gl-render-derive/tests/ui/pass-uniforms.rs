@@ -0,0 +1,39 @@
+#[path = "../support/gl_render.rs"]
+mod gl_render;
+
+mod gl {
+    pub mod types {
+        pub type GLint = i32;
+    }
+}
+
+use gl_render::uniform::{ProgramUniforms, UniformDesc};
+use gl_render_derive::ProgramUniforms;
+
+#[derive(ProgramUniforms)]
+struct Uniforms {
+    model: [f32; 16],
+    time: f32,
+}
+
+fn main() {
+    assert_eq!(
+        Uniforms::UNIFORMS,
+        &[
+            UniformDesc {
+                name: "model",
+                gl_type: 0x8b5c,
+            },
+            UniformDesc {
+                name: "time",
+                gl_type: 0x1406,
+            },
+        ]
+    );
+
+    let uniforms = Uniforms {
+        model: [0.0; 16],
+        time: 1.0,
+    };
+    uniforms.upload(&gl_render::Program, &[0, 1]);
+}
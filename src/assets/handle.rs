@@ -0,0 +1,103 @@
+use std::cell::{Ref, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+use super::Error;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    Failed,
+    // failed, but a placeholder is there in its place
+    Fallback,
+}
+
+pub(super) enum State<T> {
+    Loading,
+    Loaded(T),
+    Failed(Error),
+    // the placeholder is shared by every asset of the type that fell back
+    Fallback(Rc<T>, Error),
+}
+
+pub(super) struct Slot<T> {
+    pub name: String,
+    pub state: RefCell<State<T>>,
+}
+
+// a shared reference to an asset of an AssetServer
+//
+// every handle for the same name points at the same asset, which is dropped, and its GPU
+// objects deleted, together with the last handle
+pub struct Handle<T> {
+    slot: Rc<Slot<T>>,
+}
+
+#[allow(dead_code)]
+impl<T> Handle<T> {
+    pub(super) fn new(slot: Rc<Slot<T>>) -> Handle<T> {
+        Handle { slot }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.slot.name
+    }
+
+    pub fn load_state(&self) -> LoadState {
+        match *self.slot.state.borrow() {
+            State::Loading => LoadState::Loading,
+            State::Loaded(_) => LoadState::Loaded,
+            State::Failed(_) => LoadState::Failed,
+            State::Fallback(..) => LoadState::Fallback,
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.load_state() == LoadState::Loaded
+    }
+
+    // None until the asset is loaded, the placeholder if it fell back to one
+    pub fn get(&self) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.slot.state.borrow(), |state| match state {
+            State::Loaded(asset) => Some(asset),
+            State::Fallback(placeholder, _) => Some(&**placeholder),
+            _ => None,
+        })
+        .ok()
+    }
+
+    pub fn error(&self) -> Option<Error> {
+        match *self.slot.state.borrow() {
+            State::Failed(ref err) | State::Fallback(_, ref err) => Some(err.clone()),
+            _ => None,
+        }
+    }
+
+    // the number of handles sharing the asset, including this one
+    pub fn handle_count(&self) -> usize {
+        Rc::strong_count(&self.slot)
+    }
+
+    pub fn ptr_eq(&self, other: &Handle<T>) -> bool {
+        Rc::ptr_eq(&self.slot, &other.slot)
+    }
+}
+
+// derive would require T: Clone
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Handle<T> {
+        Handle {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("name", &self.slot.name)
+            .field("state", &self.load_state())
+            .finish()
+    }
+}
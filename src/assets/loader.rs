@@ -0,0 +1,167 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use super::AssetServer;
+
+// runs on the GL thread with what a worker produced
+pub(super) type Finish = Box<dyn FnOnce(&AssetServer) + Send>;
+
+// runs on a worker, usually reads and decodes a resource
+pub(super) struct Job {
+    pub run: Box<dyn FnOnce() -> Finish + Send>,
+    // what the GL thread gets instead if run panics, called with the panic message
+    pub panicked: Box<dyn FnOnce(String) -> Finish + Send>,
+}
+
+// submit failed because every worker is gone
+#[derive(Debug)]
+pub(super) struct Stopped;
+
+// a fixed set of worker threads, results are collected by the thread that owns the pool
+pub(super) struct Loader {
+    jobs: Option<Sender<Job>>,
+    finished: Receiver<Finish>,
+    workers: Vec<JoinHandle<()>>,
+    // set on drop, the workers skip the jobs still queued
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Loader {
+    pub fn new(threads: usize) -> Loader {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (finish_sender, finished) = mpsc::channel::<Finish>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let workers = (0..threads.max(1))
+            .map(|index| {
+                let job_receiver = job_receiver.clone();
+                let finish_sender = finish_sender.clone();
+                let cancelled = cancelled.clone();
+                thread::Builder::new()
+                    .name(format!("asset-loader-{}", index))
+                    .spawn(move || loop {
+                        // the lock is only held while waiting, not while the job runs
+                        let job = match job_receiver.lock().unwrap().recv() {
+                            Ok(job) => job,
+                            // the pool was dropped
+                            Err(_) => break,
+                        };
+                        if cancelled.load(Ordering::Relaxed) {
+                            continue;
+                        }
+
+                        // a panicking job must not take the worker, and the asset, with it
+                        let finish = match panic::catch_unwind(AssertUnwindSafe(job.run)) {
+                            Ok(finish) => finish,
+                            Err(payload) => (job.panicked)(panic_message(&*payload)),
+                        };
+                        if finish_sender.send(finish).is_err() {
+                            break;
+                        }
+                    })
+                    .expect("failed to spawn asset loader thread")
+            })
+            .collect();
+
+        Loader {
+            jobs: Some(jobs),
+            finished,
+            workers,
+            cancelled,
+        }
+    }
+
+    pub fn submit(&self, job: Job) -> Result<(), Stopped> {
+        match &self.jobs {
+            Some(jobs) => jobs.send(job).map_err(|_| Stopped),
+            None => Err(Stopped),
+        }
+    }
+
+    pub fn try_finished(&self) -> Option<Finish> {
+        self.finished.try_recv().ok()
+    }
+}
+
+impl Drop for Loader {
+    fn drop(&mut self) {
+        // closing the channel stops the workers once they are done with their current job, the
+        // jobs behind it are thrown away
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn job<F: FnOnce() + Send + 'static>(f: F) -> Job {
+        Job {
+            run: Box::new(move || {
+                f();
+                Box::new(|_: &AssetServer| ()) as Finish
+            }),
+            panicked: Box::new(|_| Box::new(|_: &AssetServer| ())),
+        }
+    }
+
+    #[test]
+    fn queued_jobs_are_dropped_with_the_loader() {
+        let loader = Loader::new(1);
+        let (release, blocked) = mpsc::channel::<()>();
+        let ran = Arc::new(AtomicUsize::new(0));
+
+        loader
+            .submit(job(move || {
+                let _ = blocked.recv();
+            }))
+            .unwrap();
+        for _ in 0..10 {
+            let ran = ran.clone();
+            loader
+                .submit(job(move || {
+                    ran.fetch_add(1, Ordering::SeqCst);
+                }))
+                .unwrap();
+        }
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            // fails if the worker skipped the blocking job as well
+            let _ = release.send(());
+        });
+        drop(loader);
+        releaser.join().unwrap();
+        assert_eq!(ran.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn panic_messages() {
+        let message = panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(&*message), "static");
+        let message = panic::catch_unwind(|| panic!("formatted {}", 1)).unwrap_err();
+        assert_eq!(panic_message(&*message), "formatted 1");
+    }
+}
@@ -0,0 +1,637 @@
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::gl_render::mesh::{self, Mesh, MeshData};
+use crate::gl_render::texture::{self, Texture};
+use crate::gl_render::{self, Builtin, Preprocessor, Program, ProgramCache, ShaderSource};
+use crate::resources::Resources;
+
+mod handle;
+mod loader;
+
+#[allow(unused_imports)]
+pub use self::handle::{Handle, LoadState};
+use self::handle::{Slot, State};
+use self::loader::{Job, Loader};
+
+#[derive(Error, Debug, Clone)]
+pub enum Error {
+    #[error("failed to load asset {name}: {inner}")]
+    Load {
+        name: String,
+        // shared by every handle of the failed asset
+        inner: Arc<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("loading asset {name} panicked: {message}")]
+    Panicked { name: String, message: String },
+    #[error("failed to load asset {name}, the loader threads stopped")]
+    LoaderStopped { name: String },
+}
+
+fn load_error<E: std::error::Error + Send + Sync + 'static>(name: &str, err: E) -> Error {
+    Error::Load {
+        name: name.into(),
+        inner: Arc::new(err),
+    }
+}
+
+// what an asset can use while decoding, on any thread
+pub struct DecodeContext<'a> {
+    pub res: &'a Resources,
+    // used for every program, shaders can only refer to vertex inputs registered here
+    pub preprocessor: &'a Preprocessor,
+}
+
+// what an asset can use while uploading, on the GL thread
+pub struct LoadContext<'a> {
+    pub gl: &'a gl::Gl,
+    pub preprocessor: &'a Preprocessor,
+    pub program_cache: Option<&'a ProgramCache>,
+}
+
+// something an AssetServer can load by resource name
+//
+// loading is split so file I/O and decoding can happen on a worker thread, and only the
+// upload to GL on the thread owning the context
+pub trait Asset: Sized + 'static {
+    type Error: std::error::Error + Send + Sync + 'static;
+    // what decode hands to upload
+    type Data: Send + 'static;
+
+    fn decode(ctx: &DecodeContext, name: &str) -> Result<Self::Data, Self::Error>;
+
+    fn upload(ctx: &LoadContext, name: &str, data: Self::Data) -> Result<Self, Self::Error>;
+
+    // what stands in for an asset that failed to load when fallbacks are enabled
+    fn fallback(ctx: &LoadContext) -> Result<Self, Self::Error>;
+}
+
+impl Asset for Texture {
+    type Error = texture::Error;
+    type Data = image::DynamicImage;
+
+    fn decode(ctx: &DecodeContext, name: &str) -> Result<image::DynamicImage, texture::Error> {
+        Ok(ctx.res.load_image(name)?)
+    }
+
+    fn upload(
+        ctx: &LoadContext,
+        name: &str,
+        image: image::DynamicImage,
+    ) -> Result<Texture, texture::Error> {
+        Texture::from_image(ctx.gl, name, image)
+    }
+
+    fn fallback(ctx: &LoadContext) -> Result<Texture, texture::Error> {
+        Texture::checkerboard(ctx.gl)
+    }
+}
+
+// name is the shared stem of the stages, like "shaders/square"
+impl Asset for Program {
+    type Error = gl_render::Error;
+    type Data = Vec<ShaderSource>;
+
+    fn decode(ctx: &DecodeContext, name: &str) -> Result<Vec<ShaderSource>, gl_render::Error> {
+        Program::sources_from_res(ctx.res, name, ctx.preprocessor)
+    }
+
+    fn upload(
+        ctx: &LoadContext,
+        name: &str,
+        sources: Vec<ShaderSource>,
+    ) -> Result<Program, gl_render::Error> {
+        let defines = ctx.preprocessor.defines();
+        Program::from_sources(ctx.gl, name, &sources, defines, ctx.program_cache, false)
+    }
+
+    // only reads the position, any vertex layout with a vec3 at location 0 can be drawn with it
+    fn fallback(ctx: &LoadContext) -> Result<Program, gl_render::Error> {
+        Program::builtin(ctx.gl, Builtin::Error)
+    }
+}
+
+// name is an OBJ file
+impl Asset for Mesh {
+    type Error = mesh::Error;
+    type Data = MeshData;
+
+    fn decode(ctx: &DecodeContext, name: &str) -> Result<MeshData, mesh::Error> {
+        MeshData::from_obj(ctx.res, name)
+    }
+
+    fn upload(ctx: &LoadContext, _name: &str, data: MeshData) -> Result<Mesh, mesh::Error> {
+        Ok(Mesh::from_data(ctx.gl, &data))
+    }
+
+    fn fallback(ctx: &LoadContext) -> Result<Mesh, mesh::Error> {
+        Ok(Mesh::from_data(ctx.gl, &MeshData::unit_cube()))
+    }
+}
+
+// how far the background loads started since the queue was last empty have come
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub finished: usize,
+    pub requested: usize,
+}
+
+#[allow(dead_code)]
+impl LoadProgress {
+    pub fn is_done(&self) -> bool {
+        self.finished == self.requested
+    }
+
+    // 1.0 when nothing was requested
+    pub fn fraction(&self) -> f32 {
+        if self.requested == 0 {
+            1.0
+        } else {
+            self.finished as f32 / self.requested as f32
+        }
+    }
+}
+
+// the assets of one type by name, only weak references so dropping the last handle frees
+// the asset
+struct Storage<T> {
+    slots: HashMap<String, Weak<Slot<T>>>,
+    // created by the first asset that falls back and kept for the ones after it
+    fallback: Option<Rc<T>>,
+}
+
+impl<T> Storage<T> {
+    fn get(&self, name: &str) -> Option<Rc<Slot<T>>> {
+        self.slots.get(name).and_then(Weak::upgrade)
+    }
+
+    fn insert(&mut self, slot: &Rc<Slot<T>>) {
+        // forget the names of assets whose handles are all gone
+        self.slots.retain(|_, slot| slot.strong_count() > 0);
+        self.slots.insert(slot.name.clone(), Rc::downgrade(slot));
+    }
+}
+
+// loads assets once and shares them between everyone asking for the same name
+//
+// assets live on the thread owning the GL context, so neither the server nor handles are Send
+pub struct AssetServer {
+    gl: gl::Gl,
+    res: Arc<Resources>,
+    // shared with the workers
+    preprocessor: Arc<Preprocessor>,
+    program_cache: Option<ProgramCache>,
+    // TypeId of T to Storage<T>
+    storages: RefCell<HashMap<TypeId, Box<dyn Any>>>,
+    // started with the first background load
+    loader: RefCell<Option<Loader>>,
+    loader_threads: usize,
+    progress: Cell<LoadProgress>,
+    fallbacks: bool,
+    // states that could not be stored while a Handle::get borrow was alive, retried by update
+    deferred: RefCell<Vec<Box<dyn FnMut() -> bool>>>,
+}
+
+#[allow(dead_code)]
+impl AssetServer {
+    pub fn new(gl: &gl::Gl, res: Arc<Resources>) -> AssetServer {
+        AssetServer {
+            gl: gl.clone(),
+            res,
+            preprocessor: Arc::new(Preprocessor::new()),
+            program_cache: None,
+            storages: RefCell::new(HashMap::new()),
+            loader: RefCell::new(None),
+            loader_threads: thread::available_parallelism()
+                .map(|threads| threads.get().min(4))
+                .unwrap_or(2),
+            progress: Cell::new(LoadProgress {
+                finished: 0,
+                requested: 0,
+            }),
+            fallbacks: false,
+            deferred: RefCell::new(Vec::new()),
+        }
+    }
+
+    // the preprocessor every program is loaded with
+    pub fn with_preprocessor(mut self, preprocessor: Preprocessor) -> AssetServer {
+        self.preprocessor = Arc::new(preprocessor);
+        self
+    }
+
+    // the number of threads for load_async, at most 4 by default
+    pub fn with_loader_threads(mut self, threads: usize) -> AssetServer {
+        self.loader_threads = threads.max(1);
+        self
+    }
+
+    // assets that fail to load are replaced by a placeholder and a warning is printed, instead
+    // of only failing, meant for development
+    pub fn with_fallbacks(mut self, fallbacks: bool) -> AssetServer {
+        self.fallbacks = fallbacks;
+        self
+    }
+
+    pub fn with_program_cache(mut self, cache: Option<ProgramCache>) -> AssetServer {
+        self.program_cache = cache;
+        self
+    }
+
+    pub fn resources(&self) -> &Arc<Resources> {
+        &self.res
+    }
+
+    // loads the asset unless a handle to it is still alive, an asset that failed before is
+    // loaded again
+    //
+    // with fallbacks enabled a failed asset is returned as a handle to its placeholder, which
+    // stays until the asset is reloaded
+    //
+    // blocks until the asset is uploaded, even if a background load of it is in flight
+    pub fn load<T: Asset>(&self, name: &str) -> Result<Handle<T>, Error> {
+        let (slot, _) = self.slot::<T>(name);
+
+        let needs_load = matches!(*slot.state.borrow(), State::Loading | State::Failed(_));
+        if needs_load {
+            self.load_slot(&slot);
+        }
+        handle_or_error(slot)
+    }
+
+    // loads the asset again whatever state it is in, e.g. after its file changed
+    //
+    // handles keep the old asset, or placeholder, until the new one is uploaded, if it fails
+    // the handles see the failure
+    pub fn reload<T: Asset>(&self, name: &str) -> Result<Handle<T>, Error> {
+        let (slot, _) = self.slot::<T>(name);
+        self.load_slot(&slot);
+        handle_or_error(slot)
+    }
+
+    // returns right away with a handle that is Loading until update uploads the asset
+    //
+    // the resource is read and decoded on a worker thread
+    pub fn load_async<T: Asset>(&self, name: &str) -> Handle<T> {
+        let (slot, created) = self.slot::<T>(name);
+        let retry = matches!(*slot.state.borrow(), State::Failed(_));
+        if retry {
+            *slot.state.borrow_mut() = State::Loading;
+        } else if !created {
+            return Handle::new(slot);
+        }
+
+        let mut progress = self.progress.get();
+        if progress.is_done() {
+            progress = LoadProgress {
+                finished: 0,
+                requested: 0,
+            };
+        }
+        progress.requested += 1;
+        self.progress.set(progress);
+
+        let res = self.res.clone();
+        let preprocessor = self.preprocessor.clone();
+        let job_name = name.to_string();
+        let panic_name = name.to_string();
+        let job = Job {
+            run: Box::new(move || {
+                let ctx = DecodeContext {
+                    res: &res,
+                    preprocessor: &preprocessor,
+                };
+                let result = T::decode(&ctx, &job_name).map_err(|e| load_error(&job_name, e));
+                Box::new(move |server: &AssetServer| server.finish_async::<T>(&job_name, result))
+            }),
+            panicked: Box::new(move |message| {
+                Box::new(move |server: &AssetServer| {
+                    let err = Error::Panicked {
+                        name: panic_name.clone(),
+                        message,
+                    };
+                    server.finish_async::<T>(&panic_name, Err(err))
+                })
+            }),
+        };
+        let submitted = self
+            .loader
+            .borrow_mut()
+            .get_or_insert_with(|| Loader::new(self.loader_threads))
+            .submit(job);
+        if submitted.is_err() {
+            let err = Error::LoaderStopped { name: name.into() };
+            self.finish_async::<T>(name, Err(err));
+        }
+
+        Handle::new(slot)
+    }
+
+    // uploads what the workers decoded, and stores assets that finished while a handle was
+    // borrowed, call once per frame on the GL thread
+    //
+    // stops starting new uploads once budget is used up, but always makes some progress, returns
+    // the number of assets handled
+    pub fn update(&self, budget: Duration) -> usize {
+        self.deferred.borrow_mut().retain_mut(|store| !store());
+
+        let start = Instant::now();
+        let mut handled = 0;
+        while handled == 0 || start.elapsed() < budget {
+            // not borrowed while finishing, uploads may start more background loads
+            let finish = match self.loader.borrow().as_ref() {
+                Some(loader) => loader.try_finished(),
+                None => None,
+            };
+            match finish {
+                Some(finish) => finish(self),
+                None => break,
+            }
+            handled += 1;
+        }
+        handled
+    }
+
+    pub fn progress(&self) -> LoadProgress {
+        self.progress.get()
+    }
+
+    // the live slot for name or a new one in the Loading state, true if it was created
+    fn slot<T: Asset>(&self, name: &str) -> (Rc<Slot<T>>, bool) {
+        match self.with_storage(|storage: &mut Storage<T>| storage.get(name)) {
+            Some(slot) => (slot, false),
+            None => {
+                let slot = Rc::new(Slot {
+                    name: name.into(),
+                    state: RefCell::new(State::Loading),
+                });
+                self.with_storage(|storage: &mut Storage<T>| storage.insert(&slot));
+                (slot, true)
+            }
+        }
+    }
+
+    fn finish_async<T: Asset>(&self, name: &str, result: Result<T::Data, Error>) {
+        let mut progress = self.progress.get();
+        progress.finished += 1;
+        self.progress.set(progress);
+
+        // nothing to do if every handle was dropped or the asset got loaded synchronously
+        if let Some(slot) = self.with_storage(|storage: &mut Storage<T>| storage.get(name)) {
+            let loading = matches!(*slot.state.borrow(), State::Loading);
+            if loading {
+                self.finish(&slot, result);
+            }
+        }
+    }
+
+    // reads, decodes and uploads the asset on this thread
+    fn load_slot<T: Asset>(&self, slot: &Rc<Slot<T>>) {
+        let ctx = DecodeContext {
+            res: &self.res,
+            preprocessor: &self.preprocessor,
+        };
+        let result = T::decode(&ctx, &slot.name).map_err(|e| load_error(&slot.name, e));
+        self.finish(slot, result);
+    }
+
+    fn finish<T: Asset>(&self, slot: &Rc<Slot<T>>, result: Result<T::Data, Error>) {
+        let ctx = LoadContext {
+            gl: &self.gl,
+            preprocessor: &self.preprocessor,
+            program_cache: self.program_cache.as_ref(),
+        };
+        let uploaded = result.and_then(|data| {
+            T::upload(&ctx, &slot.name, data).map_err(|e| load_error(&slot.name, e))
+        });
+        let state = match uploaded {
+            Ok(asset) => State::Loaded(asset),
+            Err(err) => {
+                if self.fallbacks {
+                    match self.fallback::<T>(&ctx) {
+                        Ok(fallback) => {
+                            eprintln!("warning: {}, using a placeholder", err);
+                            State::Fallback(fallback, err)
+                        }
+                        Err(fallback_err) => {
+                            eprintln!(
+                                "warning: no placeholder for {}: {}",
+                                slot.name, fallback_err
+                            );
+                            State::Failed(err)
+                        }
+                    }
+                } else {
+                    State::Failed(err)
+                }
+            }
+        };
+        self.store(slot, state);
+    }
+
+    // a Ref returned by Handle::get may still be alive, then the state is stored by a later
+    // update instead
+    fn store<T: Asset>(&self, slot: &Rc<Slot<T>>, state: State<T>) {
+        // keeps the states of one slot in order
+        if self.deferred.borrow().is_empty() {
+            if let Ok(mut current) = slot.state.try_borrow_mut() {
+                *current = state;
+                return;
+            }
+        }
+
+        let slot = slot.clone();
+        let mut state = Some(state);
+        self.deferred
+            .borrow_mut()
+            .push(Box::new(move || match slot.state.try_borrow_mut() {
+                Ok(mut current) => {
+                    *current = state.take().unwrap();
+                    true
+                }
+                Err(_) => false,
+            }));
+    }
+
+    // the placeholder of T, created the first time an asset of type T falls back
+    fn fallback<T: Asset>(&self, ctx: &LoadContext) -> Result<Rc<T>, T::Error> {
+        let existing = self.with_storage(|storage: &mut Storage<T>| storage.fallback.clone());
+        if let Some(fallback) = existing {
+            return Ok(fallback);
+        }
+        let fallback = Rc::new(T::fallback(ctx)?);
+        self.with_storage(|storage: &mut Storage<T>| storage.fallback = Some(fallback.clone()));
+        Ok(fallback)
+    }
+
+    // the state of the asset if any handle to it is alive
+    pub fn load_state<T: Asset>(&self, name: &str) -> Option<LoadState> {
+        self.with_storage(|storage: &mut Storage<T>| storage.get(name))
+            .map(|slot| Handle::new(slot).load_state())
+    }
+
+    // the names of the assets of type T that are alive
+    pub fn loaded<T: Asset>(&self) -> Vec<String> {
+        self.with_storage(|storage: &mut Storage<T>| {
+            storage
+                .slots
+                .iter()
+                .filter(|(_, slot)| slot.strong_count() > 0)
+                .map(|(name, _)| name.clone())
+                .collect()
+        })
+    }
+
+    fn with_storage<T: Asset, R, F: FnOnce(&mut Storage<T>) -> R>(&self, f: F) -> R {
+        let mut storages = self.storages.borrow_mut();
+        let storage = storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                Box::new(Storage::<T> {
+                    slots: HashMap::new(),
+                    fallback: None,
+                })
+            })
+            .downcast_mut::<Storage<T>>()
+            .unwrap();
+        f(storage)
+    }
+}
+
+fn handle_or_error<T>(slot: Rc<Slot<T>>) -> Result<Handle<T>, Error> {
+    let handle = Handle::new(slot);
+    match handle.load_state() {
+        LoadState::Failed => Err(handle.error().unwrap()),
+        _ => Ok(handle),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::{self, MemorySource};
+
+    thread_local! {
+        static DECODES: Cell<usize> = const { Cell::new(0) };
+        static UPLOADS: Cell<usize> = const { Cell::new(0) };
+        static FALLBACKS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // an asset that does not need GL, counting what the server does with it
+    struct Text {
+        text: String,
+        // the number of uploads on this thread when it was uploaded
+        upload: usize,
+    }
+
+    impl Asset for Text {
+        type Error = resources::Error;
+        type Data = String;
+
+        fn decode(ctx: &DecodeContext, name: &str) -> Result<String, resources::Error> {
+            DECODES.with(|decodes| decodes.set(decodes.get() + 1));
+            if name == "panic.txt" {
+                panic!("decoding {} panicked", name);
+            }
+            ctx.res.load_string(name)
+        }
+
+        fn upload(_ctx: &LoadContext, _name: &str, text: String) -> Result<Text, resources::Error> {
+            let upload = UPLOADS.with(|uploads| {
+                uploads.set(uploads.get() + 1);
+                uploads.get()
+            });
+            Ok(Text { text, upload })
+        }
+
+        fn fallback(_ctx: &LoadContext) -> Result<Text, resources::Error> {
+            FALLBACKS.with(|fallbacks| fallbacks.set(fallbacks.get() + 1));
+            Ok(Text {
+                text: "placeholder".into(),
+                upload: 0,
+            })
+        }
+    }
+
+    fn server(source: MemorySource) -> AssetServer {
+        // Text never calls into GL
+        let gl = gl::Gl::load_with(|_| std::ptr::null());
+        AssetServer::new(&gl, Arc::new(Resources::new(source)))
+    }
+
+    #[test]
+    fn fallbacks_share_one_placeholder_and_are_only_retried_by_reload() {
+        let server = server(MemorySource::new()).with_fallbacks(true);
+
+        let a = server.load::<Text>("a.txt").unwrap();
+        let b = server.load::<Text>("b.txt").unwrap();
+        assert_eq!(a.load_state(), LoadState::Fallback);
+        assert_eq!(b.get().unwrap().text, "placeholder");
+        assert!(std::ptr::eq(&*a.get().unwrap(), &*b.get().unwrap()));
+        assert_eq!(FALLBACKS.with(Cell::get), 1);
+
+        let decodes = DECODES.with(Cell::get);
+        server.load::<Text>("a.txt").unwrap();
+        server.load_async::<Text>("a.txt");
+        assert_eq!(DECODES.with(Cell::get), decodes);
+
+        server.reload::<Text>("a.txt").unwrap();
+        assert_eq!(DECODES.with(Cell::get), decodes + 1);
+        assert_eq!(a.load_state(), LoadState::Fallback);
+        assert_eq!(FALLBACKS.with(Cell::get), 1);
+    }
+
+    #[test]
+    fn states_finished_while_a_handle_is_borrowed_wait_for_update() {
+        let server = server(MemorySource::new().with("a.txt", "text"));
+        let handle = server.load::<Text>("a.txt").unwrap();
+
+        let text = handle.get().unwrap();
+        assert_eq!(text.upload, UPLOADS.with(Cell::get));
+        server.reload::<Text>("a.txt").unwrap();
+        server.update(Duration::from_millis(0));
+        assert_eq!(text.upload, UPLOADS.with(Cell::get) - 1);
+        drop(text);
+
+        server.update(Duration::from_millis(0));
+        assert_eq!(handle.get().unwrap().upload, UPLOADS.with(Cell::get));
+        assert_eq!(handle.get().unwrap().text, "text");
+    }
+
+    // updates until every background load finished
+    fn wait(server: &AssetServer) {
+        let start = Instant::now();
+        while !server.progress().is_done() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "loads did not finish"
+            );
+            server.update(Duration::from_millis(1));
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn panicking_decodes_fail_the_asset_and_keep_the_worker() {
+        let server = server(MemorySource::new().with("a.txt", "text")).with_loader_threads(1);
+
+        let panicked = server.load_async::<Text>("panic.txt");
+        wait(&server);
+        assert_eq!(panicked.load_state(), LoadState::Failed);
+        assert!(matches!(
+            panicked.error(),
+            Some(Error::Panicked { message, .. }) if message == "decoding panic.txt panicked"
+        ));
+
+        let loaded = server.load_async::<Text>("a.txt");
+        wait(&server);
+        assert_eq!(loaded.get().unwrap().text, "text");
+    }
+}
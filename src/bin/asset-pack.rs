@@ -0,0 +1,146 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+#[path = "../resources/pack_format.rs"]
+mod pack_format;
+
+use pack_format::{Compression, IgnorePatterns, PackWriter};
+
+const USAGE: &str = "usage: asset-pack [--store] [--preprocess] <assets dir> <output .pack>
+
+files matching a pattern in <assets dir>/.assetignore are left out
+
+    --store       do not compress anything
+    --preprocess  turn \\r\\n into \\n in text resources like shaders and OBJ files";
+
+// resources with these extensions are text and get their line endings normalized
+const TEXT_EXTENSIONS: &[&str] = &[
+    "vert", "frag", "geom", "comp", "glsl", "obj", "mtl", "ron", "json", "toml", "txt",
+];
+
+struct Options {
+    compression: Compression,
+    preprocess: bool,
+    assets_dir: PathBuf,
+    output: PathBuf,
+}
+
+fn main() -> Result<()> {
+    let options = parse_args(env::args().skip(1)).map_err(|e| anyhow!("{}\n\n{}", e, USAGE))?;
+
+    let ignore = IgnorePatterns::from_dir(&options.assets_dir).with_context(|| {
+        format!(
+            "failed to read the ignore file of {}",
+            options.assets_dir.display()
+        )
+    })?;
+    let mut files = Vec::new();
+    collect_files(&options.assets_dir, &options.assets_dir, &mut files)?;
+    files.retain(|(name, _)| !ignore.is_ignored(name));
+    files.sort();
+
+    let mut writer = PackWriter::new();
+    for (name, path) in &files {
+        let mut data =
+            fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        if options.preprocess && is_text(path) {
+            data = normalize_line_endings(&data);
+        }
+
+        let entry = writer.add(name, &data, options.compression)?;
+        println!(
+            "{:>10} -> {:>10}  {}",
+            entry.size, entry.packed_size, entry.name
+        );
+    }
+
+    let out = File::create(&options.output)
+        .with_context(|| format!("failed to create {}", options.output.display()))?;
+    let manifest = writer
+        .write(&mut BufWriter::new(out))
+        .with_context(|| format!("failed to write {}", options.output.display()))?;
+    println!(
+        "packed {} resources into {}",
+        manifest.entries.len(),
+        options.output.display()
+    );
+
+    Ok(())
+}
+
+fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Options> {
+    let mut compression = Compression::Deflate;
+    let mut preprocess = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--store" => compression = Compression::None,
+            "--preprocess" => preprocess = true,
+            "-h" | "--help" => bail!("asset-pack writes a directory into an asset pack"),
+            _ if arg.starts_with("--") => bail!("unknown option {}", arg),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    match paths.len() {
+        2 => {
+            let output = paths.pop().unwrap();
+            let assets_dir = paths.pop().unwrap();
+            Ok(Options {
+                compression,
+                preprocess,
+                assets_dir,
+                output,
+            })
+        }
+        _ => bail!("expected an assets directory and an output file"),
+    }
+}
+
+// every file below dir with its resource name relative to root
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) -> Result<()> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("failed to read directory {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+            continue;
+        }
+
+        let name = path
+            .strip_prefix(root)?
+            .components()
+            .map(|part| {
+                part.as_os_str()
+                    .to_str()
+                    .ok_or_else(|| anyhow!("{} is not UTF-8", path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .join("/");
+        files.push((name, path));
+    }
+    Ok(())
+}
+
+fn is_text(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => TEXT_EXTENSIONS.contains(&ext),
+        None => false,
+    }
+}
+
+fn normalize_line_endings(data: &[u8]) -> Vec<u8> {
+    let mut normalized = Vec::with_capacity(data.len());
+    for (index, &byte) in data.iter().enumerate() {
+        if byte == b'\r' && data.get(index + 1) == Some(&b'\n') {
+            continue;
+        }
+        normalized.push(byte);
+    }
+    normalized
+}
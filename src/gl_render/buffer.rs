@@ -1,3 +1,5 @@
+use super::std430::StorageBlock;
+
 pub struct VertexArray {
     gl: gl::Gl,
     vao: gl::types::GLuint,
@@ -7,7 +9,7 @@ impl VertexArray {
     pub fn new(gl: &gl::Gl) -> VertexArray {
         let mut vao: gl::types::GLuint = 0;
         unsafe {
-            gl.GenVertexArrays(1, &mut vao);
+            gl.CreateVertexArrays(1, &mut vao);
         }
 
         VertexArray {
@@ -16,6 +18,125 @@ impl VertexArray {
         }
     }
 
+    pub fn enable_attrib(&self, location: usize) {
+        unsafe {
+            self.gl
+                .EnableVertexArrayAttrib(self.vao, location as gl::types::GLuint);
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn disable_attrib(&self, location: usize) {
+        unsafe {
+            self.gl
+                .DisableVertexArrayAttrib(self.vao, location as gl::types::GLuint);
+        }
+    }
+
+    // describes the layout of an attribute relative to the start of a vertex
+    pub fn attrib_format(
+        &self,
+        location: usize,
+        size: usize,
+        ty: gl::types::GLenum,
+        normalized: bool,
+        relative_offset: usize,
+    ) {
+        unsafe {
+            self.gl.VertexArrayAttribFormat(
+                self.vao,
+                location as gl::types::GLuint,
+                size as gl::types::GLint,
+                ty,
+                if normalized { gl::TRUE } else { gl::FALSE },
+                relative_offset as gl::types::GLuint,
+            );
+        }
+    }
+
+    // integer attribute read as ivec/uvec without conversion to float
+    pub fn attrib_i_format(
+        &self,
+        location: usize,
+        size: usize,
+        ty: gl::types::GLenum,
+        relative_offset: usize,
+    ) {
+        unsafe {
+            self.gl.VertexArrayAttribIFormat(
+                self.vao,
+                location as gl::types::GLuint,
+                size as gl::types::GLint,
+                ty,
+                relative_offset as gl::types::GLuint,
+            );
+        }
+    }
+
+    // double precision attribute read as dvec
+    pub fn attrib_l_format(
+        &self,
+        location: usize,
+        size: usize,
+        ty: gl::types::GLenum,
+        relative_offset: usize,
+    ) {
+        unsafe {
+            self.gl.VertexArrayAttribLFormat(
+                self.vao,
+                location as gl::types::GLuint,
+                size as gl::types::GLint,
+                ty,
+                relative_offset as gl::types::GLuint,
+            );
+        }
+    }
+
+    // sources an attribute from the buffer attached at a binding index
+    pub fn attrib_binding(&self, location: usize, binding: usize) {
+        unsafe {
+            self.gl.VertexArrayAttribBinding(
+                self.vao,
+                location as gl::types::GLuint,
+                binding as gl::types::GLuint,
+            );
+        }
+    }
+
+    // attaches a buffer to a binding index, can be swapped without touching the format
+    pub fn vertex_buffer<B>(&self, binding: usize, buffer: &Buffer<B>, offset: usize, stride: usize)
+    where
+        B: BufferType,
+    {
+        unsafe {
+            self.gl.VertexArrayVertexBuffer(
+                self.vao,
+                binding as gl::types::GLuint,
+                buffer.id(),
+                offset as gl::types::GLintptr,
+                stride as gl::types::GLsizei,
+            );
+        }
+    }
+
+    // advance the binding once per divisor instances instead of once per vertex
+    #[allow(dead_code)]
+    pub fn binding_divisor(&self, binding: usize, divisor: usize) {
+        unsafe {
+            self.gl.VertexArrayBindingDivisor(
+                self.vao,
+                binding as gl::types::GLuint,
+                divisor as gl::types::GLuint,
+            );
+        }
+    }
+
+    pub fn element_buffer(&self, buffer: &ElementArrayBuffer) {
+        unsafe {
+            self.gl.VertexArrayElementBuffer(self.vao, buffer.id());
+        }
+    }
+
     pub fn bind(&self) {
         unsafe {
             self.gl.BindVertexArray(self.vao);
@@ -32,7 +153,7 @@ impl VertexArray {
 impl Drop for VertexArray {
     fn drop(&mut self) {
         unsafe {
-            self.gl.DeleteVertexArrays(1, &mut self.vao);
+            self.gl.DeleteVertexArrays(1, &self.vao);
         }
     }
 }
@@ -43,13 +164,18 @@ impl BufferType for BufferTypeArray {
     const BUFFER_TYPE: gl::types::GLuint = gl::ARRAY_BUFFER;
 }
 
-#[allow(dead_code)]
 pub type ElementArrayBuffer = Buffer<BufferTypeElementArray>;
 pub struct BufferTypeElementArray;
 impl BufferType for BufferTypeElementArray {
     const BUFFER_TYPE: gl::types::GLuint = gl::ELEMENT_ARRAY_BUFFER;
 }
 
+pub type ShaderStorageBufferObject = Buffer<BufferTypeShaderStorage>;
+pub struct BufferTypeShaderStorage;
+impl BufferType for BufferTypeShaderStorage {
+    const BUFFER_TYPE: gl::types::GLuint = gl::SHADER_STORAGE_BUFFER;
+}
+
 pub struct Buffer<B>
 where
     B: BufferType,
@@ -66,7 +192,7 @@ where
     pub fn new(gl: &gl::Gl) -> Buffer<B> {
         let mut vbo: gl::types::GLuint = 0;
         unsafe {
-            gl.GenBuffers(1, &mut vbo);
+            gl.CreateBuffers(1, &mut vbo);
         }
         Buffer {
             gl: gl.clone(),
@@ -75,41 +201,192 @@ where
         }
     }
 
+    // creates a buffer with immutable storage initialized from data. GL does not allow empty
+    // storage, so no data gets one uninitialized byte
+    pub fn from_data<T>(gl: &gl::Gl, data: &[T]) -> Buffer<B> {
+        let buffer = Buffer::new(gl);
+        let (size, ptr) = match std::mem::size_of_val(data) {
+            0 => (1, std::ptr::null()),
+            size => (size, data.as_ptr() as *const gl::types::GLvoid),
+        };
+        unsafe {
+            gl.NamedBufferStorage(buffer.vbo, size as gl::types::GLsizeiptr, ptr, 0);
+        }
+        buffer
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.vbo
+    }
+
+    #[allow(dead_code)]
     pub fn bind(&self) {
         unsafe {
             self.gl.BindBuffer(B::BUFFER_TYPE, self.vbo);
         }
     }
 
+    #[allow(dead_code)]
     pub fn unbind(&self) {
         unsafe {
             self.gl.BindBuffer(B::BUFFER_TYPE, 0);
         }
     }
+}
 
-    pub fn static_draw_data<T>(&self, data: &[T]) {
+impl<B> Drop for Buffer<B>
+where
+    B: BufferType,
+{
+    fn drop(&mut self) {
         unsafe {
-            self.gl.BufferData(
-                B::BUFFER_TYPE,
-                (data.len() * std::mem::size_of::<T>()) as gl::types::GLsizeiptr,
-                data.as_ptr() as *const gl::types::GLvoid,
-                gl::STATIC_DRAW,
-            );
+            self.gl.DeleteBuffers(1, &self.vbo);
         }
     }
 }
 
-impl<B> Drop for Buffer<B>
+// a shader storage buffer holding one S followed by len elements of its runtime-sized array
+pub struct ShaderStorageBuffer<S>
 where
-    B: BufferType,
+    S: StorageBlock,
 {
-    fn drop(&mut self) {
+    buffer: ShaderStorageBufferObject,
+    len: usize,
+    _marker: std::marker::PhantomData<S>,
+}
+
+#[allow(dead_code)]
+impl<S> ShaderStorageBuffer<S>
+where
+    S: StorageBlock,
+{
+    pub fn new(gl: &gl::Gl, block: &S, elements: &[S::Element]) -> ShaderStorageBuffer<S> {
+        // fails to compile if the Rust layout of S is not std430
+        let _ = S::ALIGN;
+
+        let buffer = Buffer::new(gl);
+        let size = storage_size::<S>(elements.len());
         unsafe {
-            self.gl.DeleteBuffers(1, &mut self.vbo);
+            gl.NamedBufferStorage(
+                buffer.vbo,
+                size as gl::types::GLsizeiptr,
+                std::ptr::null(),
+                gl::DYNAMIC_STORAGE_BIT,
+            );
         }
+
+        let storage = ShaderStorageBuffer {
+            buffer,
+            len: elements.len(),
+            _marker: std::marker::PhantomData,
+        };
+        storage.set_block(block);
+        storage.set_elements(0, elements);
+        storage
+    }
+
+    // the number of elements in the runtime-sized array
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn set_block(&self, block: &S) {
+        unsafe {
+            self.buffer.gl.NamedBufferSubData(
+                self.buffer.vbo,
+                0,
+                S::ELEMENT_OFFSET as gl::types::GLsizeiptr,
+                block as *const S as *const gl::types::GLvoid,
+            );
+        }
+    }
+
+    pub fn set_elements(&self, first: usize, elements: &[S::Element]) {
+        assert!(
+            first + elements.len() <= self.len,
+            "elements {}..{} are out of bounds for a storage buffer of {} elements",
+            first,
+            first + elements.len(),
+            self.len
+        );
+        if elements.is_empty() {
+            return;
+        }
+
+        let offset = S::ELEMENT_OFFSET + first * std::mem::size_of::<S::Element>();
+        unsafe {
+            self.buffer.gl.NamedBufferSubData(
+                self.buffer.vbo,
+                offset as gl::types::GLintptr,
+                std::mem::size_of_val(elements) as gl::types::GLsizeiptr,
+                elements.as_ptr() as *const gl::types::GLvoid,
+            );
+        }
+    }
+
+    // binds the buffer to the shader storage block declared with layout (binding = index)
+    pub fn bind_base(&self, index: usize) {
+        unsafe {
+            self.buffer.gl.BindBufferBase(
+                gl::SHADER_STORAGE_BUFFER,
+                index as gl::types::GLuint,
+                self.buffer.vbo,
+            );
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.buffer.id()
     }
 }
 
+// the bytes a storage buffer needs for the block and len elements. Without a runtime-sized array
+// the Rust size of the block can be below its std430 size, a lone vec3 takes 12 bytes in Rust and
+// 16 in GLSL, and GL does not allow empty storage
+fn storage_size<S>(len: usize) -> usize
+where
+    S: StorageBlock,
+{
+    (S::ELEMENT_OFFSET + len * std::mem::size_of::<S::Element>())
+        .max(S::SIZE)
+        .max(1)
+}
+
 pub trait BufferType {
     const BUFFER_TYPE: gl::types::GLuint;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gl_render_derive::StorageBlock;
+    use nalgebra_glm as glm;
+
+    #[derive(Copy, Clone, StorageBlock)]
+    #[repr(C)]
+    struct Direction {
+        value: glm::Vec3,
+    }
+
+    #[derive(Copy, Clone, StorageBlock)]
+    #[repr(C)]
+    struct Points {
+        count: u32,
+        #[padding]
+        _pad: [u32; 3],
+        points: [glm::Vec4; 0],
+    }
+
+    #[test]
+    fn storage_covers_the_std430_size_of_the_block() {
+        assert_eq!(std::mem::size_of::<Direction>(), 12);
+        assert_eq!(storage_size::<Direction>(0), 16);
+
+        assert_eq!(storage_size::<Points>(0), 16);
+        assert_eq!(storage_size::<Points>(3), 64);
+    }
+}
@@ -0,0 +1,116 @@
+use gl_render_derive::VertexAttribPointers;
+
+use super::data;
+use super::preprocessor::{Defines, Preprocessor};
+use super::shader::{Error, Program, ShaderSource};
+
+const VERTEX_SOURCE: &str = include_str!("shaders/builtin.vert");
+
+// programs that are compiled from sources embedded in the binary, so drawing does not need
+// an assets directory
+//
+// all of them read BuiltinVertex and transform it with the mat4 uniforms model, view and
+// projection
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    // a single color, uniform vec4 color
+    UnlitColor,
+    // uniform sampler2D tex
+    Textured,
+    // the color attribute of each vertex
+    VertexColor,
+    // one point light, uniforms vec4 color, vec3 light_position, vec3 light_color,
+    // vec3 view_position, float ambient and float shininess
+    BlinnPhong,
+    // world space normals mapped to rgb
+    NormalView,
+    // texture coordinates mapped to red and green
+    UvView,
+    // solid magenta, stands in for programs that failed to build
+    Error,
+}
+
+impl Builtin {
+    #[allow(dead_code)]
+    pub const ALL: [Builtin; 7] = [
+        Builtin::UnlitColor,
+        Builtin::Textured,
+        Builtin::VertexColor,
+        Builtin::BlinnPhong,
+        Builtin::NormalView,
+        Builtin::UvView,
+        Builtin::Error,
+    ];
+
+    // the name errors refer to the fragment stage by
+    pub fn fragment_name(self) -> &'static str {
+        match self {
+            Builtin::UnlitColor => "builtin/unlit.frag",
+            Builtin::Textured => "builtin/textured.frag",
+            Builtin::VertexColor => "builtin/vertex_color.frag",
+            Builtin::BlinnPhong => "builtin/blinn_phong.frag",
+            Builtin::NormalView => "builtin/normals.frag",
+            Builtin::UvView => "builtin/uvs.frag",
+            Builtin::Error => "builtin/error.frag",
+        }
+    }
+
+    pub fn fragment_source(self) -> &'static str {
+        match self {
+            Builtin::UnlitColor => include_str!("shaders/unlit.frag"),
+            Builtin::Textured => include_str!("shaders/textured.frag"),
+            Builtin::VertexColor => include_str!("shaders/vertex_color.frag"),
+            Builtin::BlinnPhong => include_str!("shaders/blinn_phong.frag"),
+            Builtin::NormalView => include_str!("shaders/normals.frag"),
+            Builtin::UvView => include_str!("shaders/uvs.frag"),
+            Builtin::Error => include_str!("shaders/error.frag"),
+        }
+    }
+}
+
+// the vertex layout every builtin program reads
+#[derive(Copy, Clone, Debug, VertexAttribPointers)]
+#[repr(C, packed)]
+pub struct BuiltinVertex {
+    position: data::vec3,
+    normal: data::vec3,
+    texcoord: data::vec2,
+    color: data::vec4,
+}
+
+impl BuiltinVertex {
+    pub fn new(
+        position: (f32, f32, f32),
+        normal: (f32, f32, f32),
+        texcoord: (f32, f32),
+        color: (f32, f32, f32, f32),
+    ) -> BuiltinVertex {
+        BuiltinVertex {
+            position: position.into(),
+            normal: normal.into(),
+            texcoord: texcoord.into(),
+            color: color.into(),
+        }
+    }
+}
+
+impl Program {
+    pub fn builtin(gl: &gl::Gl, kind: Builtin) -> Result<Program, Error> {
+        let preprocessor =
+            Preprocessor::new().with_vertex_inputs("BuiltinVertex", BuiltinVertex::GLSL_INPUTS);
+        let sources = [
+            ShaderSource::from_str("builtin/builtin.vert", VERTEX_SOURCE, &preprocessor)?,
+            ShaderSource::from_str(kind.fragment_name(), kind.fragment_source(), &preprocessor)?,
+        ];
+
+        Program::from_sources(
+            gl,
+            kind.fragment_name(),
+            &sources,
+            &Defines::new(),
+            None,
+            false,
+        )
+    }
+}
@@ -1,62 +1,319 @@
-#[derive(Copy, Clone, Debug)]
-#[repr(C, packed)]
-pub struct vec2 {
-    pub x: f32,
-    pub y: f32,
-}
-
-impl vec2 {
-    pub fn new(x: f32, y: f32) -> vec2 {
-        vec2 { x, y }
-    }
-
-    pub unsafe fn vertex_attrib_pointer(
-        gl: &gl::Gl,
-        stride: usize,
-        location: usize,
-        offset: usize,
-    ) {
-        gl.EnableVertexAttribArray(location as gl::types::GLuint);
-        gl.VertexAttribPointer(
-            location as gl::types::GLuint,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            stride as gl::types::GLint,
-            offset as *const gl::types::GLvoid,
-        );
+use half::f16;
+
+use nalgebra_glm as glm;
+
+use super::buffer::VertexArray;
+
+// how the shader sees the attribute data
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AttributePath {
+    // converted to float, normalized or not
+    Float,
+    // read as ivec/uvec
+    Integer,
+    // read as dvec
+    Double,
+}
+
+// describes one field of a vertex struct, generated by the vertex derive
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VertexAttributeDesc {
+    pub name: &'static str,
+    // first location, matrices continue on the following ones
+    pub location: usize,
+    pub locations: usize,
+    // components per location
+    pub components: usize,
+    pub gl_type: gl::types::GLenum,
+    pub normalized: bool,
+    pub path: AttributePath,
+    // byte offset of the field inside the vertex struct
+    pub offset: usize,
+}
+
+impl VertexAttributeDesc {
+    pub const fn glsl_base_type(&self) -> GlslBaseType {
+        match self.path {
+            AttributePath::Float => GlslBaseType::Float,
+            AttributePath::Double => GlslBaseType::Double,
+            AttributePath::Integer => match self.gl_type {
+                gl::UNSIGNED_BYTE | gl::UNSIGNED_SHORT | gl::UNSIGNED_INT => GlslBaseType::Uint,
+                _ => GlslBaseType::Int,
+            },
+        }
+    }
+
+    // the type of the matching vertex shader input, e.g. vec3 or mat4
+    pub const fn glsl_type(&self) -> &'static str {
+        glsl_type_name(self.glsl_base_type(), self.components, self.locations)
     }
 }
 
-impl From<(f32, f32)> for vec2 {
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GlslBaseType {
+    Float,
+    Int,
+    Uint,
+    Double,
+}
+
+pub const fn glsl_type_name(base: GlslBaseType, components: usize, columns: usize) -> &'static str {
+    use GlslBaseType::*;
+    match (base, components, columns) {
+        (Float, 1, 1) => "float",
+        (Float, 2, 1) => "vec2",
+        (Float, 3, 1) => "vec3",
+        (Float, 4, 1) => "vec4",
+        (Float, 2, 2) => "mat2",
+        (Float, 3, 3) => "mat3",
+        (Float, 4, 4) => "mat4",
+        (Float, 3, 2) => "mat2x3",
+        (Float, 4, 2) => "mat2x4",
+        (Float, 2, 3) => "mat3x2",
+        (Float, 4, 3) => "mat3x4",
+        (Float, 2, 4) => "mat4x2",
+        (Float, 3, 4) => "mat4x3",
+        (Int, 1, 1) => "int",
+        (Int, 2, 1) => "ivec2",
+        (Int, 3, 1) => "ivec3",
+        (Int, 4, 1) => "ivec4",
+        (Uint, 1, 1) => "uint",
+        (Uint, 2, 1) => "uvec2",
+        (Uint, 3, 1) => "uvec3",
+        (Uint, 4, 1) => "uvec4",
+        (Double, 1, 1) => "double",
+        (Double, 2, 1) => "dvec2",
+        (Double, 3, 1) => "dvec3",
+        (Double, 4, 1) => "dvec4",
+        (Double, 2, 2) => "dmat2",
+        (Double, 3, 3) => "dmat3",
+        (Double, 4, 4) => "dmat4",
+        (Double, 3, 2) => "dmat2x3",
+        (Double, 4, 2) => "dmat2x4",
+        (Double, 2, 3) => "dmat3x2",
+        (Double, 4, 3) => "dmat3x4",
+        (Double, 2, 4) => "dmat4x2",
+        (Double, 3, 4) => "dmat4x3",
+        _ => "<unknown>",
+    }
+}
+
+// longest line glsl_inputs can write for an attribute, excluding the name
+pub const GLSL_INPUT_LINE_OVERHEAD: usize =
+    "layout (location = ) in  ;\n".len() + 20 + "<unknown>".len();
+
+// writes the vertex shader input declarations for a layout at compile time, N must be large
+// enough to hold them, the vertex derive sizes it with GLSL_INPUT_LINE_OVERHEAD
+pub const fn glsl_inputs<const N: usize>(layout: &[VertexAttributeDesc]) -> ([u8; N], usize) {
+    const fn push(buffer: &mut [u8], mut len: usize, bytes: &[u8]) -> usize {
+        let mut i = 0;
+        while i < bytes.len() {
+            buffer[len] = bytes[i];
+            len += 1;
+            i += 1;
+        }
+        len
+    }
+
+    const fn push_usize(buffer: &mut [u8], len: usize, value: usize) -> usize {
+        let mut digits = [0u8; 20];
+        let mut start = digits.len();
+        let mut value = value;
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (value % 10) as u8;
+            value /= 10;
+            if value == 0 {
+                break;
+            }
+        }
+        push(buffer, len, digits.split_at(start).1)
+    }
+
+    let mut buffer = [0u8; N];
+    let mut len = 0;
+    let mut i = 0;
+    while i < layout.len() {
+        let desc = &layout[i];
+        len = push(&mut buffer, len, b"layout (location = ");
+        len = push_usize(&mut buffer, len, desc.location);
+        len = push(&mut buffer, len, b") in ");
+        len = push(&mut buffer, len, desc.glsl_type().as_bytes());
+        len = push(&mut buffer, len, b" ");
+        len = push(&mut buffer, len, desc.name.as_bytes());
+        len = push(&mut buffer, len, b";\n");
+        i += 1;
+    }
+    (buffer, len)
+}
+
+pub const fn glsl_inputs_str(buffer: &'static [u8], len: usize) -> &'static str {
+    match std::str::from_utf8(buffer.split_at(len).0) {
+        Ok(inputs) => inputs,
+        Err(_) => panic!("glsl inputs are not valid utf-8"),
+    }
+}
+
+// a type that can be used as a field of a vertex struct
+pub trait VertexAttribute: Sized {
+    // components per attribute location
+    const COMPONENTS: usize;
+    const GL_TYPE: gl::types::GLenum;
+    const NORMALIZED: bool = false;
+    const PATH: AttributePath = AttributePath::Float;
+    // consecutive locations used, matrices take one per column
+    const LOCATIONS: usize = 1;
+
+    fn vertex_attrib_format(vao: &VertexArray, location: usize, offset: usize) {
+        let column_size = std::mem::size_of::<Self>() / Self::LOCATIONS;
+        for column in 0..Self::LOCATIONS {
+            let location = location + column;
+            let offset = offset + column * column_size;
+            vao.enable_attrib(location);
+            match Self::PATH {
+                AttributePath::Float => vao.attrib_format(
+                    location,
+                    Self::COMPONENTS,
+                    Self::GL_TYPE,
+                    Self::NORMALIZED,
+                    offset,
+                ),
+                AttributePath::Integer => {
+                    vao.attrib_i_format(location, Self::COMPONENTS, Self::GL_TYPE, offset)
+                }
+                AttributePath::Double => {
+                    vao.attrib_l_format(location, Self::COMPONENTS, Self::GL_TYPE, offset)
+                }
+            }
+        }
+    }
+}
+
+// declares a plain vector attribute
+macro_rules! vector_attribute {
+    ($name:ident, $component:ty, $gl_type:expr, $path:ident, [$($field:ident),+]) => {
+        #[allow(dead_code)]
+        #[derive(Copy, Clone, Debug)]
+        #[repr(C, packed)]
+        pub struct $name {
+            $(pub $field: $component,)+
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            pub fn new($($field: $component),+) -> $name {
+                $name { $($field),+ }
+            }
+        }
+
+        impl VertexAttribute for $name {
+            const COMPONENTS: usize = [$(stringify!($field)),+].len();
+            const GL_TYPE: gl::types::GLenum = $gl_type;
+            const PATH: AttributePath = AttributePath::$path;
+        }
+
+        impl From<($(vector_attribute!(@component $field, $component),)+)> for $name {
+            fn from(($($field,)+): ($(vector_attribute!(@component $field, $component),)+)) -> Self {
+                $name::new($($field),+)
+            }
+        }
+    };
+    (@component $field:ident, $component:ty) => { $component };
+}
+
+vector_attribute!(vec2, f32, gl::FLOAT, Float, [x, y]);
+vector_attribute!(vec3, f32, gl::FLOAT, Float, [x, y, z]);
+vector_attribute!(vec4, f32, gl::FLOAT, Float, [x, y, z, w]);
+
+vector_attribute!(ivec2, i32, gl::INT, Integer, [x, y]);
+vector_attribute!(ivec3, i32, gl::INT, Integer, [x, y, z]);
+vector_attribute!(ivec4, i32, gl::INT, Integer, [x, y, z, w]);
+
+vector_attribute!(uvec2, u32, gl::UNSIGNED_INT, Integer, [x, y]);
+vector_attribute!(uvec3, u32, gl::UNSIGNED_INT, Integer, [x, y, z]);
+vector_attribute!(uvec4, u32, gl::UNSIGNED_INT, Integer, [x, y, z, w]);
+
+vector_attribute!(dvec2, f64, gl::DOUBLE, Double, [x, y]);
+vector_attribute!(dvec3, f64, gl::DOUBLE, Double, [x, y, z]);
+vector_attribute!(dvec4, f64, gl::DOUBLE, Double, [x, y, z, w]);
+
+vector_attribute!(hvec2, f16, gl::HALF_FLOAT, Float, [x, y]);
+vector_attribute!(hvec3, f16, gl::HALF_FLOAT, Float, [x, y, z]);
+vector_attribute!(hvec4, f16, gl::HALF_FLOAT, Float, [x, y, z, w]);
+
+impl From<(f32, f32)> for hvec2 {
     fn from((x, y): (f32, f32)) -> Self {
-        vec2::new(x, y)
+        hvec2::new(f16::from_f32(x), f16::from_f32(y))
+    }
+}
+
+impl From<(f32, f32, f32)> for hvec3 {
+    fn from((x, y, z): (f32, f32, f32)) -> Self {
+        hvec3::new(f16::from_f32(x), f16::from_f32(y), f16::from_f32(z))
+    }
+}
+
+impl From<(f32, f32, f32, f32)> for hvec4 {
+    fn from((x, y, z, w): (f32, f32, f32, f32)) -> Self {
+        hvec4::new(
+            f16::from_f32(x),
+            f16::from_f32(y),
+            f16::from_f32(z),
+            f16::from_f32(w),
+        )
     }
 }
 
+// declares an integer vector that the shader reads as a normalized float vector,
+// unsigned components map to [0, 1] and signed components to [-1, 1]
+macro_rules! normalized_attribute {
+    ($name:ident, $component:ty, $gl_type:expr, [$($field:ident),+]) => {
+        #[allow(dead_code)]
+        #[derive(Copy, Clone, Debug)]
+        #[repr(C, packed)]
+        pub struct $name {
+            $(pub $field: $component,)+
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            pub fn new($($field: $component),+) -> $name {
+                $name { $($field),+ }
+            }
+        }
+
+        impl VertexAttribute for $name {
+            const COMPONENTS: usize = [$(stringify!($field)),+].len();
+            const GL_TYPE: gl::types::GLenum = $gl_type;
+            const NORMALIZED: bool = true;
+        }
+
+        impl From<($(normalized_attribute!(@f32 $field),)+)> for $name {
+            fn from(($($field,)+): ($(normalized_attribute!(@f32 $field),)+)) -> Self {
+                const MIN: f32 = if <$component>::MIN == 0 { 0.0 } else { -1.0 };
+                $name::new($(($field.clamp(MIN, 1.0) * <$component>::MAX as f32).round() as $component),+)
+            }
+        }
+    };
+    (@f32 $field:ident) => { f32 };
+}
+
+normalized_attribute!(U8U8U8U8Float, u8, gl::UNSIGNED_BYTE, [x, y, z, w]);
+normalized_attribute!(I8I8I8I8Float, i8, gl::BYTE, [x, y, z, w]);
+normalized_attribute!(U16U16Float, u16, gl::UNSIGNED_SHORT, [x, y]);
+normalized_attribute!(I16I16I16I16Float, i16, gl::SHORT, [x, y, z, w]);
+
 #[derive(Copy, Clone, Debug)]
 #[repr(C, packed)]
 pub struct U2U10U10U10RevFloat {
     pub inner: vec_2_10_10_10::Vector,
 }
 
-impl U2U10U10U10RevFloat {
-    pub unsafe fn vertex_attrib_pointer(
-        gl: &gl::Gl,
-        stride: usize,
-        location: usize,
-        offset: usize,
-    ) {
-        gl.EnableVertexAttribArray(location as gl::types::GLuint);
-        gl.VertexAttribPointer(
-            location as gl::types::GLuint,
-            4,
-            gl::UNSIGNED_INT_2_10_10_10_REV,
-            gl::TRUE,
-            stride as gl::types::GLint,
-            offset as *const gl::types::GLvoid,
-        );
-    }
+impl VertexAttribute for U2U10U10U10RevFloat {
+    const COMPONENTS: usize = 4;
+    const GL_TYPE: gl::types::GLenum = gl::UNSIGNED_INT_2_10_10_10_REV;
+    const NORMALIZED: bool = true;
 }
 
 impl From<(f32, f32, f32, f32)> for U2U10U10U10RevFloat {
@@ -67,39 +324,199 @@ impl From<(f32, f32, f32, f32)> for U2U10U10U10RevFloat {
     }
 }
 
+// signed counterpart of U2U10U10U10RevFloat, mostly useful for packed normals
+#[allow(dead_code)]
 #[derive(Copy, Clone, Debug)]
 #[repr(C, packed)]
-pub struct vec3 {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-}
-
-impl vec3 {
-    pub fn new(x: f32, y: f32, z: f32) -> vec3 {
-        vec3 { x, y, z }
-    }
-
-    pub unsafe fn vertex_attrib_pointer(
-        gl: &gl::Gl,
-        stride: usize,
-        location: usize,
-        offset: usize,
-    ) {
-        gl.EnableVertexAttribArray(location as gl::types::GLuint);
-        gl.VertexAttribPointer(
-            location as gl::types::GLuint,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            stride as gl::types::GLint,
-            offset as *const gl::types::GLvoid,
-        );
+pub struct I2I10I10I10RevFloat {
+    pub inner: u32,
+}
+
+impl VertexAttribute for I2I10I10I10RevFloat {
+    const COMPONENTS: usize = 4;
+    const GL_TYPE: gl::types::GLenum = gl::INT_2_10_10_10_REV;
+    const NORMALIZED: bool = true;
+}
+
+impl From<(f32, f32, f32, f32)> for I2I10I10I10RevFloat {
+    fn from((x, y, z, w): (f32, f32, f32, f32)) -> Self {
+        fn pack(value: f32, bits: u32) -> u32 {
+            let max = ((1 << (bits - 1)) - 1) as f32;
+            let value = (value.clamp(-1.0, 1.0) * max).round() as i32;
+            (value as u32) & ((1 << bits) - 1)
+        }
+
+        I2I10I10I10RevFloat {
+            inner: pack(x, 10) | pack(y, 10) << 10 | pack(z, 10) << 20 | pack(w, 2) << 30,
+        }
     }
 }
 
-impl From<(f32, f32, f32)> for vec3 {
-    fn from((x, y, z): (f32, f32, f32)) -> Self {
-        vec3::new(x, y, z)
+// matrices are stored column major and take one attribute location per column
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug)]
+#[repr(C, packed)]
+pub struct mat3 {
+    pub cols: [vec3; 3],
+}
+
+impl VertexAttribute for mat3 {
+    const COMPONENTS: usize = 3;
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT;
+    const LOCATIONS: usize = 3;
+}
+
+impl From<glm::Mat3> for mat3 {
+    fn from(other: glm::Mat3) -> Self {
+        let col = |i: usize| vec3::new(other[(0, i)], other[(1, i)], other[(2, i)]);
+        mat3 {
+            cols: [col(0), col(1), col(2)],
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug)]
+#[repr(C, packed)]
+pub struct mat4 {
+    pub cols: [vec4; 4],
+}
+
+impl VertexAttribute for mat4 {
+    const COMPONENTS: usize = 4;
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT;
+    const LOCATIONS: usize = 4;
+}
+
+impl From<glm::Mat4> for mat4 {
+    fn from(other: glm::Mat4) -> Self {
+        let col = |i: usize| vec4::new(other[(0, i)], other[(1, i)], other[(2, i)], other[(3, i)]);
+        mat4 {
+            cols: [col(0), col(1), col(2), col(3)],
+        }
+    }
+}
+
+impl VertexAttribute for glm::Vec2 {
+    const COMPONENTS: usize = 2;
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT;
+}
+
+impl VertexAttribute for glm::Vec3 {
+    const COMPONENTS: usize = 3;
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT;
+}
+
+impl VertexAttribute for glm::Vec4 {
+    const COMPONENTS: usize = 4;
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT;
+}
+
+impl VertexAttribute for [f32; 1] {
+    const COMPONENTS: usize = 1;
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT;
+}
+
+impl VertexAttribute for [f32; 2] {
+    const COMPONENTS: usize = 2;
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT;
+}
+
+impl VertexAttribute for [f32; 3] {
+    const COMPONENTS: usize = 3;
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT;
+}
+
+impl VertexAttribute for [f32; 4] {
+    const COMPONENTS: usize = 4;
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT;
+}
+
+// usually an rgba color, read as a normalized vec4
+impl VertexAttribute for [u8; 4] {
+    const COMPONENTS: usize = 4;
+    const GL_TYPE: gl::types::GLenum = gl::UNSIGNED_BYTE;
+    const NORMALIZED: bool = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gl_render_derive::VertexAttribPointers;
+
+    #[derive(Copy, Clone, Debug, VertexAttribPointers)]
+    #[repr(C, packed)]
+    struct Mixed {
+        position: vec3,
+        transform: mat4,
+        id: ivec2,
+        flags: uvec4,
+        color: U8U8U8U8Float,
+        #[location = 12]
+        weight: dvec3,
+    }
+
+    #[test]
+    fn derive_writes_one_input_per_field() {
+        assert_eq!(
+            Mixed::GLSL_INPUTS,
+            "layout (location = 0) in vec3 position;\n\
+             layout (location = 1) in mat4 transform;\n\
+             layout (location = 5) in ivec2 id;\n\
+             layout (location = 6) in uvec4 flags;\n\
+             layout (location = 7) in vec4 color;\n\
+             layout (location = 12) in dvec3 weight;\n"
+        );
+    }
+
+    fn desc(name: &'static str, location: usize, components: usize) -> VertexAttributeDesc {
+        VertexAttributeDesc {
+            name,
+            location,
+            locations: 1,
+            components,
+            gl_type: gl::BYTE,
+            normalized: false,
+            path: AttributePath::Integer,
+            offset: 0,
+        }
+    }
+
+    #[test]
+    fn signedness_follows_the_gl_type() {
+        let unsigned = VertexAttributeDesc {
+            gl_type: gl::UNSIGNED_SHORT,
+            ..desc("a", 0, 2)
+        };
+        assert_eq!(desc("a", 0, 2).glsl_type(), "ivec2");
+        assert_eq!(unsigned.glsl_type(), "uvec2");
+    }
+
+    // the line overhead has to cover the longest location and type name
+    #[test]
+    fn line_overhead_fits_the_longest_line() {
+        const LAYOUT: &[VertexAttributeDesc] = &[VertexAttributeDesc {
+            name: "x",
+            location: usize::MAX,
+            locations: 1,
+            components: 5,
+            gl_type: gl::FLOAT,
+            normalized: false,
+            path: AttributePath::Float,
+            offset: 0,
+        }];
+        const BUFFER: ([u8; GLSL_INPUT_LINE_OVERHEAD + 1], usize) = glsl_inputs(LAYOUT);
+        let inputs = glsl_inputs_str(&BUFFER.0, BUFFER.1);
+        assert_eq!(
+            inputs,
+            format!("layout (location = {}) in <unknown> x;\n", usize::MAX)
+        );
+        assert_eq!(inputs.len(), BUFFER.0.len());
+    }
+
+    #[test]
+    fn empty_layout_writes_nothing() {
+        const BUFFER: ([u8; 0], usize) = glsl_inputs(&[]);
+        assert_eq!(glsl_inputs_str(&BUFFER.0, BUFFER.1), "");
     }
 }
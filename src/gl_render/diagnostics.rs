@@ -0,0 +1,350 @@
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+// one message of a driver info log
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    // the source string index, always 0 as every shader is compiled from one string
+    pub file: Option<usize>,
+    pub line: Option<usize>,
+    // 1 based, only reported by Mesa
+    pub column: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+enum LogLine {
+    Diagnostic(Diagnostic),
+    // summaries like AMD's "ERROR: 1 compilation errors.  No code generated."
+    Skip,
+    Unrecognized,
+}
+
+// splits an info log into diagnostics, understands the formats of
+//
+// Mesa:   0:12(5): error: message
+// NVIDIA: 0(12) : error C0000: message
+// AMD:    ERROR: 0:12: message
+//
+// lines in other formats continue the previous diagnostic or become one without a location
+pub fn parse_info_log(log: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    for line in log.lines() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match parse_line(line) {
+            LogLine::Diagnostic(diagnostic) => diagnostics.push(diagnostic),
+            LogLine::Skip => (),
+            LogLine::Unrecognized => match diagnostics.last_mut() {
+                Some(previous) => {
+                    previous.message.push('\n');
+                    previous.message.push_str(line.trim());
+                }
+                None => diagnostics.push(Diagnostic {
+                    file: None,
+                    line: None,
+                    column: None,
+                    severity: if line.to_lowercase().contains("warning") {
+                        Severity::Warning
+                    } else {
+                        Severity::Error
+                    },
+                    message: line.trim().into(),
+                }),
+            },
+        }
+    }
+
+    diagnostics
+}
+
+fn parse_line(line: &str) -> LogLine {
+    if let Some(diagnostic) = parse_amd(line) {
+        return diagnostic;
+    }
+    if let Some(diagnostic) = parse_mesa(line).or_else(|| parse_nvidia(line)) {
+        return LogLine::Diagnostic(diagnostic);
+    }
+    LogLine::Unrecognized
+}
+
+// ERROR: 0:12: message
+fn parse_amd(line: &str) -> Option<LogLine> {
+    let (severity, rest) = if let Some(rest) = line.strip_prefix("ERROR:") {
+        (Severity::Error, rest)
+    } else if let Some(rest) = line.strip_prefix("WARNING:") {
+        (Severity::Warning, rest)
+    } else {
+        return None;
+    };
+
+    let located = split_number(rest.trim_start()).and_then(|(file, rest)| {
+        let (line, rest) = split_number(rest.strip_prefix(':')?)?;
+        Some((file, line, rest.strip_prefix(':')?))
+    });
+    match located {
+        Some((file, line, message)) => Some(LogLine::Diagnostic(Diagnostic {
+            file: Some(file),
+            line: Some(line),
+            column: None,
+            severity,
+            message: message.trim().into(),
+        })),
+        None => Some(LogLine::Skip),
+    }
+}
+
+// 0:12(5): error: message
+fn parse_mesa(line: &str) -> Option<Diagnostic> {
+    let (file, rest) = split_number(line.trim_start())?;
+    let (line, rest) = split_number(rest.strip_prefix(':')?)?;
+    let (column, rest) = split_number(rest.strip_prefix('(')?)?;
+    let rest = rest.strip_prefix("):")?;
+    let (severity, message) = split_severity(rest)?;
+
+    Some(Diagnostic {
+        file: Some(file),
+        line: Some(line),
+        column: Some(column),
+        severity,
+        message: message.trim().into(),
+    })
+}
+
+// 0(12) : error C0000: message
+fn parse_nvidia(line: &str) -> Option<Diagnostic> {
+    let (file, rest) = split_number(line.trim_start())?;
+    let (line, rest) = split_number(rest.strip_prefix('(')?)?;
+    let rest = rest.strip_prefix(')')?.trim_start().strip_prefix(':')?;
+    let (severity, message) = split_severity(rest)?;
+
+    Some(Diagnostic {
+        file: Some(file),
+        line: Some(line),
+        column: None,
+        severity,
+        message: message.trim().into(),
+    })
+}
+
+fn split_number(s: &str) -> Option<(usize, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let number = s[..end].parse().ok()?;
+    Some((number, &s[end..]))
+}
+
+// "error: message" or "error C0000: message"
+fn split_severity(s: &str) -> Option<(Severity, &str)> {
+    let (kind, message) = s.split_once(':')?;
+    let severity = match kind.split_whitespace().next()?.to_lowercase().as_str() {
+        "error" => Severity::Error,
+        "warning" => Severity::Warning,
+        "info" | "note" => Severity::Note,
+        _ => return None,
+    };
+    Some((severity, message))
+}
+
+// shows every diagnostic with the source line it points at
+//
+// error: shaders/square.frag:12:5: message
+//    12 | void main( {
+//       |     ^
+pub fn render(name: &str, source: &str, diagnostics: &[Diagnostic]) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut output = String::new();
+
+    for diagnostic in diagnostics {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+
+        let source_line = diagnostic
+            .line
+            .and_then(|line| lines.get(line.checked_sub(1)?).map(|text| (line, *text)));
+        let (line, text) = match (diagnostic.line, source_line) {
+            (Some(_), Some(source_line)) => source_line,
+            (Some(line), None) => {
+                output.push_str(&format!(
+                    "{}: {}:{}: {}",
+                    diagnostic.severity, name, line, diagnostic.message
+                ));
+                continue;
+            }
+            (None, _) => {
+                output.push_str(&format!(
+                    "{}: {}: {}",
+                    diagnostic.severity, name, diagnostic.message
+                ));
+                continue;
+            }
+        };
+
+        // without a column point at the start of the statement
+        let column = diagnostic
+            .column
+            .filter(|&column| column > 0)
+            .unwrap_or_else(|| text.len() - text.trim_start().len() + 1);
+        let location = match diagnostic.column {
+            Some(column) => format!("{}:{}:{}", name, line, column),
+            None => format!("{}:{}", name, line),
+        };
+        let gutter = line.to_string().len();
+
+        output.push_str(&format!(
+            "{}: {}: {}\n",
+            diagnostic.severity, location, diagnostic.message
+        ));
+        output.push_str(&format!(" {} | {}\n", line, text.replace('\t', " ")));
+        output.push_str(&format!(
+            " {:gutter$} | {:column$}^",
+            "",
+            "",
+            gutter = gutter,
+            column = column - 1
+        ));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn located(
+        line: usize,
+        column: Option<usize>,
+        severity: Severity,
+        message: &str,
+    ) -> Diagnostic {
+        Diagnostic {
+            file: Some(0),
+            line: Some(line),
+            column,
+            severity,
+            message: message.into(),
+        }
+    }
+
+    fn unlocated(severity: Severity, message: &str) -> Diagnostic {
+        Diagnostic {
+            file: None,
+            line: None,
+            column: None,
+            severity,
+            message: message.into(),
+        }
+    }
+
+    #[test]
+    fn parse_info_log_formats() {
+        let cases = [
+            // NVIDIA
+            (
+                "0(12) : error C0000: syntax error, unexpected '}'\n",
+                vec![located(
+                    12,
+                    None,
+                    Severity::Error,
+                    "syntax error, unexpected '}'",
+                )],
+            ),
+            (
+                "0(3) : warning C7050: \"color\" might be used before being initialized",
+                vec![located(
+                    3,
+                    None,
+                    Severity::Warning,
+                    "\"color\" might be used before being initialized",
+                )],
+            ),
+            // Mesa
+            (
+                "0:12(3): error: syntax error, unexpected '}'\n\
+                 0:14(1): warning: `unused' declared but not used\n",
+                vec![
+                    located(12, Some(3), Severity::Error, "syntax error, unexpected '}'"),
+                    located(
+                        14,
+                        Some(1),
+                        Severity::Warning,
+                        "`unused' declared but not used",
+                    ),
+                ],
+            ),
+            // AMD, with the summary skipped
+            (
+                "ERROR: 0:12: 'colour' : undeclared identifier\n\
+                 WARNING: 0:2: extension not supported\n\
+                 ERROR: 1 compilation errors.  No code generated.\n",
+                vec![
+                    located(
+                        12,
+                        None,
+                        Severity::Error,
+                        "'colour' : undeclared identifier",
+                    ),
+                    located(2, None, Severity::Warning, "extension not supported"),
+                ],
+            ),
+            // unknown lines continue the previous diagnostic
+            (
+                "0:5(10): error: no matching function for call to `mix(vec3, float)'\n\
+                 \x20 candidates are: vec3 mix(vec3, vec3, vec3)\n",
+                vec![located(
+                    5,
+                    Some(10),
+                    Severity::Error,
+                    "no matching function for call to `mix(vec3, float)'\n\
+                     candidates are: vec3 mix(vec3, vec3, vec3)",
+                )],
+            ),
+            // or become diagnostics of their own if they come first
+            (
+                "Vertex shader failed to compile with the following errors:\n",
+                vec![unlocated(
+                    Severity::Error,
+                    "Vertex shader failed to compile with the following errors:",
+                )],
+            ),
+            (
+                "warning: the driver falls back to software\n",
+                vec![unlocated(
+                    Severity::Warning,
+                    "warning: the driver falls back to software",
+                )],
+            ),
+            // a location followed by something that is no severity is not a diagnostic
+            (
+                "0(12) : something C0000: else",
+                vec![unlocated(Severity::Error, "0(12) : something C0000: else")],
+            ),
+            ("\n  \n", vec![]),
+        ];
+
+        for (log, expected) in &cases {
+            assert_eq!(&parse_info_log(log), expected, "for {:?}", log);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::io::BufReader;
+
+use thiserror::Error;
+
+use super::buffer::{ArrayBuffer, ElementArrayBuffer, VertexArray};
+use super::builtin::BuiltinVertex;
+use crate::resources::{self, Resources};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to load resource {name}: {inner}")]
+    ResourceLoad {
+        name: String,
+        inner: resources::Error,
+    },
+    #[error("failed to parse OBJ {name}: {inner}")]
+    Obj {
+        name: String,
+        inner: tobj::LoadError,
+    },
+}
+
+// the vertices and indices of a mesh before they are uploaded
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub vertices: Vec<BuiltinVertex>,
+    pub indices: Vec<u32>,
+}
+
+#[allow(dead_code)]
+impl MeshData {
+    // every model of the file is merged into one mesh, faces are triangulated and missing
+    // normals and texture coordinates are zero, the vertex color is the diffuse color of the
+    // model's material or white
+    pub fn from_obj(res: &Resources, name: &str) -> Result<MeshData, Error> {
+        let bytes = res.load_bytes(name).map_err(|e| Error::ResourceLoad {
+            name: name.into(),
+            inner: e,
+        })?;
+
+        // material libraries are looked up next to the OBJ, a missing one only loses colors
+        let dir = match name.rfind('/') {
+            Some(index) => &name[..=index],
+            None => "",
+        };
+        let (models, materials) =
+            tobj::load_obj_buf(&mut BufReader::new(&bytes[..]), true, |path| {
+                let mtl_name = format!("{}{}", dir, path.to_string_lossy());
+                match res.load_bytes(&mtl_name) {
+                    Ok(bytes) => tobj::load_mtl_buf(&mut BufReader::new(&bytes[..])),
+                    Err(_) => Ok((Vec::new(), HashMap::new())),
+                }
+            })
+            .map_err(|e| Error::Obj {
+                name: name.into(),
+                inner: e,
+            })?;
+
+        let mut data = MeshData::default();
+        for model in models {
+            let mesh = &model.mesh;
+            let color = mesh
+                .material_id
+                .and_then(|id| materials.get(id))
+                .map(|material| {
+                    let [r, g, b] = material.diffuse;
+                    (r, g, b, material.dissolve)
+                })
+                .unwrap_or((1.0, 1.0, 1.0, 1.0));
+
+            let first = data.vertices.len() as u32;
+            for index in 0..mesh.positions.len() / 3 {
+                let vec3 = |values: &[f32]| match values.get(index * 3..index * 3 + 3) {
+                    Some(v) => (v[0], v[1], v[2]),
+                    None => (0.0, 0.0, 0.0),
+                };
+                let texcoord = match mesh.texcoords.get(index * 2..index * 2 + 2) {
+                    Some(v) => (v[0], v[1]),
+                    None => (0.0, 0.0),
+                };
+                data.vertices.push(BuiltinVertex::new(
+                    vec3(&mesh.positions),
+                    vec3(&mesh.normals),
+                    texcoord,
+                    color,
+                ));
+            }
+            data.indices
+                .extend(mesh.indices.iter().map(|&index| first + index));
+        }
+
+        Ok(data)
+    }
+
+    // a white cube from -0.5 to 0.5 with a normal and the full texture per face, stands in for
+    // meshes that failed to load
+    pub fn unit_cube() -> MeshData {
+        // normal and the directions of u and v of each face
+        const FACES: [[(f32, f32, f32); 3]; 6] = [
+            [(1.0, 0.0, 0.0), (0.0, 0.0, -1.0), (0.0, 1.0, 0.0)],
+            [(-1.0, 0.0, 0.0), (0.0, 0.0, 1.0), (0.0, 1.0, 0.0)],
+            [(0.0, 1.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, -1.0)],
+            [(0.0, -1.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, 1.0)],
+            [(0.0, 0.0, 1.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+            [(0.0, 0.0, -1.0), (-1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+        ];
+
+        let mut data = MeshData::default();
+        for &[n, u, v] in FACES.iter() {
+            let first = data.vertices.len() as u32;
+            for &(s, t) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)].iter() {
+                let (a, b) = (s - 0.5, t - 0.5);
+                let position = (
+                    n.0 * 0.5 + u.0 * a + v.0 * b,
+                    n.1 * 0.5 + u.1 * a + v.1 * b,
+                    n.2 * 0.5 + u.2 * a + v.2 * b,
+                );
+                data.vertices.push(BuiltinVertex::new(
+                    position,
+                    n,
+                    (s, t),
+                    (1.0, 1.0, 1.0, 1.0),
+                ));
+            }
+            // counter clockwise seen from outside
+            data.indices
+                .extend([0, 1, 2, 2, 1, 3].iter().map(|&index| first + index));
+        }
+        data
+    }
+}
+
+// indexed triangles in BuiltinVertex layout, drawable with any builtin program
+pub struct Mesh {
+    _vbo: ArrayBuffer,
+    _ebo: ElementArrayBuffer,
+    vao: VertexArray,
+    index_count: usize,
+    gl: gl::Gl,
+}
+
+#[allow(dead_code)]
+impl Mesh {
+    pub fn from_data(gl: &gl::Gl, data: &MeshData) -> Mesh {
+        let vbo = ArrayBuffer::from_data(gl, &data.vertices);
+        let ebo = ElementArrayBuffer::from_data(gl, &data.indices);
+
+        let vao = VertexArray::new(gl);
+        BuiltinVertex::vertex_format(&vao, 0);
+        vao.vertex_buffer(0, &vbo, 0, std::mem::size_of::<BuiltinVertex>());
+        vao.element_buffer(&ebo);
+
+        Mesh {
+            _vbo: vbo,
+            _ebo: ebo,
+            vao,
+            index_count: data.indices.len(),
+            gl: gl.clone(),
+        }
+    }
+
+    pub fn from_obj(gl: &gl::Gl, res: &Resources, name: &str) -> Result<Mesh, Error> {
+        Ok(Mesh::from_data(gl, &MeshData::from_obj(res, name)?))
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.index_count
+    }
+
+    // expects the program to be in use
+    pub fn draw(&self) {
+        // an obj without faces has nothing to draw
+        if self.index_count == 0 {
+            return;
+        }
+        self.vao.bind();
+        unsafe {
+            self.gl.DrawElements(
+                gl::TRIANGLES,
+                self.index_count as gl::types::GLsizei,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+        self.vao.unbind();
+    }
+}
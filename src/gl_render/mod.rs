@@ -1,10 +1,35 @@
 mod shader;
-pub use self::shader::{Error, Program, Shader};
+#[allow(unused_imports)]
+pub use self::shader::{
+    ActiveAttribute, ActiveBufferVariable, ActiveStorageBlock, ActiveUniform, Error, Program,
+    ProgramVariants, Shader, ShaderSource, StorageBlockProblem, UniformProblem, VertexLayoutProblem,
+};
+
+pub mod builtin;
+#[allow(unused_imports)]
+pub use self::builtin::{Builtin, BuiltinVertex};
+
+pub mod diagnostics;
+
+pub mod preprocessor;
+#[allow(unused_imports)]
+pub use self::preprocessor::{Defines, Preprocessor};
+
+mod pipeline;
+#[allow(unused_imports)]
+pub use self::pipeline::ProgramPipeline;
+
+mod program_cache;
+pub use self::program_cache::ProgramCache;
 
 pub mod buffer;
 
 pub mod data;
 
+pub mod mesh;
+
+pub mod std430;
+
 mod viewport;
 pub use self::viewport::Viewport;
 
@@ -12,5 +37,9 @@ pub mod color_buffer;
 
 pub mod texture;
 
+pub mod uniform;
+pub use self::uniform::Uniforms;
+
 mod transform;
+#[allow(unused_imports)]
 pub use self::transform::Transform;
@@ -0,0 +1,97 @@
+use super::shader::Program;
+
+// combines stages of separable programs without linking them together, e.g. one vertex
+// program with several fragment programs
+//
+// a pipeline is only used for drawing while no program is bound with Program::set_used
+pub struct ProgramPipeline {
+    gl: gl::Gl,
+    id: gl::types::GLuint,
+}
+
+#[allow(dead_code)]
+impl ProgramPipeline {
+    pub fn new(gl: &gl::Gl) -> ProgramPipeline {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl.CreateProgramPipelines(1, &mut id);
+        }
+
+        ProgramPipeline { gl: gl.clone(), id }
+    }
+
+    // uses every stage the separable program was linked with
+    pub fn use_program(&self, program: &Program) {
+        self.use_stages(program.stages(), program);
+    }
+
+    // stages is a combination of the *_SHADER_BIT constants
+    pub fn use_stages(&self, stages: gl::types::GLbitfield, program: &Program) {
+        debug_assert!(
+            program.is_separable(),
+            "only separable programs can be used in a pipeline"
+        );
+        unsafe {
+            self.gl.UseProgramStages(self.id, stages, program.id());
+        }
+    }
+
+    pub fn clear_stages(&self, stages: gl::types::GLbitfield) {
+        unsafe {
+            self.gl.UseProgramStages(self.id, stages, 0);
+        }
+    }
+
+    // checks that the stages fit together and can be drawn with the current GL state
+    pub fn validate(&self) -> Result<(), String> {
+        let mut status: gl::types::GLint = 0;
+        let mut len: gl::types::GLint = 0;
+        unsafe {
+            self.gl.ValidateProgramPipeline(self.id);
+            self.gl
+                .GetProgramPipelineiv(self.id, gl::VALIDATE_STATUS, &mut status);
+            self.gl
+                .GetProgramPipelineiv(self.id, gl::INFO_LOG_LENGTH, &mut len);
+        }
+        if status != 0 {
+            return Ok(());
+        }
+
+        let mut log: Vec<u8> = vec![0; len.max(1) as usize];
+        let mut written: gl::types::GLsizei = 0;
+        unsafe {
+            self.gl.GetProgramPipelineInfoLog(
+                self.id,
+                log.len() as gl::types::GLsizei,
+                &mut written,
+                log.as_mut_ptr() as *mut gl::types::GLchar,
+            );
+        }
+        log.truncate(written.max(0) as usize);
+        Err(String::from_utf8_lossy(&log).trim_end().to_string())
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            self.gl.BindProgramPipeline(self.id);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            self.gl.BindProgramPipeline(0);
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+}
+
+impl Drop for ProgramPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteProgramPipelines(1, &self.id);
+        }
+    }
+}
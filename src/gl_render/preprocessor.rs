@@ -0,0 +1,321 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("line {line}: no vertex inputs registered for {vertex}")]
+    UnknownVertexInputs { line: usize, vertex: String },
+    #[error("line {line}: malformed #pragma vertex_inputs, expected #pragma vertex_inputs(Name)")]
+    MalformedVertexInputs { line: usize },
+    #[error("invalid define {name}, names must be identifiers and values a single line")]
+    InvalidDefine { name: String },
+}
+
+// a set of #define NAME VALUE lines selecting one variant of a shader
+//
+// kept sorted so the same set always produces the same source, and with it the same
+// cache keys
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Defines {
+    defines: BTreeMap<String, String>,
+}
+
+#[allow(dead_code)]
+impl Defines {
+    pub fn new() -> Defines {
+        Defines::default()
+    }
+
+    // #define name
+    pub fn with(self, name: &str) -> Defines {
+        self.with_value(name, "")
+    }
+
+    // #define name value
+    pub fn with_value<V: ToString>(mut self, name: &str, value: V) -> Defines {
+        self.defines.insert(name.into(), value.to_string());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defines.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.defines
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+impl fmt::Display for Defines {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, (name, value)) in self.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            if value.is_empty() {
+                write!(f, "{}", name)?;
+            } else {
+                write!(f, "{}={}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// rewrites shader sources before they are handed to the driver
+//
+// supported directives:
+// #pragma vertex_inputs(Name) - replaced by the GLSL registered for Name, usually the
+//                               GLSL_INPUTS generated by the vertex derive
+//
+// defines are inserted right after the #version line, or at the top if there is none
+#[derive(Clone, Debug, Default)]
+pub struct Preprocessor {
+    vertex_inputs: HashMap<String, String>,
+    defines: Defines,
+}
+
+impl Preprocessor {
+    pub fn new() -> Preprocessor {
+        Preprocessor::default()
+    }
+
+    pub fn with_vertex_inputs(mut self, name: &str, glsl: &str) -> Preprocessor {
+        self.vertex_inputs.insert(name.into(), glsl.into());
+        self
+    }
+
+    pub fn with_defines(mut self, defines: &Defines) -> Preprocessor {
+        self.defines = defines.clone();
+        self
+    }
+
+    pub fn defines(&self) -> &Defines {
+        &self.defines
+    }
+
+    pub fn process(&self, source: &str) -> Result<String, Error> {
+        let mut output = String::with_capacity(source.len());
+
+        // #version has to come first, so defines go right after it
+        let mut defines_pending = source.lines().any(is_version_line);
+        if !defines_pending {
+            self.push_defines(&mut output, 1)?;
+        }
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            if defines_pending && is_version_line(line) {
+                output.push_str(line);
+                output.push('\n');
+                self.push_defines(&mut output, line_number + 1)?;
+                defines_pending = false;
+                continue;
+            }
+
+            match parse_vertex_inputs_pragma(line, line_number)? {
+                Some(vertex) => {
+                    let inputs = self.vertex_inputs.get(vertex).ok_or_else(|| {
+                        Error::UnknownVertexInputs {
+                            line: line_number,
+                            vertex: vertex.into(),
+                        }
+                    })?;
+                    output.push_str(inputs);
+                    if !inputs.ends_with('\n') {
+                        output.push('\n');
+                    }
+                    // keep driver messages pointing at the original line numbers
+                    output.push_str(&format!("#line {}\n", line_number + 1));
+                }
+                None => {
+                    output.push_str(line);
+                    output.push('\n');
+                }
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn push_defines(&self, output: &mut String, next_line: usize) -> Result<(), Error> {
+        if self.defines.is_empty() {
+            return Ok(());
+        }
+
+        for (name, value) in self.defines.iter() {
+            if !is_identifier(name) || value.contains(&['\n', '\0'][..]) {
+                return Err(Error::InvalidDefine { name: name.into() });
+            }
+            if value.is_empty() {
+                output.push_str(&format!("#define {}\n", name));
+            } else {
+                output.push_str(&format!("#define {} {}\n", name, value));
+            }
+        }
+        output.push_str(&format!("#line {}\n", next_line));
+        Ok(())
+    }
+}
+
+fn is_version_line(line: &str) -> bool {
+    line.trim_start().starts_with("#version")
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn parse_vertex_inputs_pragma(line: &str, line_number: usize) -> Result<Option<&str>, Error> {
+    let rest = match line.trim().strip_prefix("#pragma") {
+        Some(rest) => rest.trim_start(),
+        None => return Ok(None),
+    };
+    let rest = match rest.strip_prefix("vertex_inputs") {
+        Some(rest) => rest.trim(),
+        None => return Ok(None),
+    };
+
+    rest.strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+        .map(str::trim)
+        .filter(|vertex| !vertex.is_empty())
+        .map(Some)
+        .ok_or(Error::MalformedVertexInputs { line: line_number })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUTS: &str = "layout (location = 0) in vec3 position;\n\
+                          layout (location = 1) in vec2 texcoord;\n";
+
+    #[test]
+    fn vertex_inputs_pragma_is_replaced() {
+        let preprocessor = Preprocessor::new().with_vertex_inputs("Vertex", INPUTS);
+        let source = "#version 450 core\n#pragma vertex_inputs(Vertex)\nvoid main() {}\n";
+        assert_eq!(
+            preprocessor.process(source).unwrap(),
+            format!("#version 450 core\n{}#line 3\nvoid main() {{}}\n", INPUTS)
+        );
+    }
+
+    #[test]
+    fn vertex_inputs_pragma_allows_whitespace() {
+        let preprocessor = Preprocessor::new().with_vertex_inputs("Vertex", "in vec3 position;");
+        assert_eq!(
+            preprocessor
+                .process("  #pragma  vertex_inputs ( Vertex ) \n")
+                .unwrap(),
+            "in vec3 position;\n#line 2\n"
+        );
+    }
+
+    #[test]
+    fn other_pragmas_are_kept() {
+        let source = "#pragma optimize(off)\n#pragma debug(on)\n";
+        assert_eq!(Preprocessor::new().process(source).unwrap(), source);
+    }
+
+    #[test]
+    fn unknown_vertex_inputs() {
+        match Preprocessor::new().process("\n#pragma vertex_inputs(Missing)\n") {
+            Err(Error::UnknownVertexInputs { line: 2, vertex }) => assert_eq!(vertex, "Missing"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn malformed_vertex_inputs() {
+        for line in &[
+            "#pragma vertex_inputs",
+            "#pragma vertex_inputs()",
+            "#pragma vertex_inputs(Vertex",
+        ] {
+            match Preprocessor::new().process(line) {
+                Err(Error::MalformedVertexInputs { line: 1 }) => (),
+                other => panic!("unexpected {:?} for {}", other, line),
+            }
+        }
+    }
+
+    #[test]
+    fn defines_go_first_without_a_version() {
+        let preprocessor = Preprocessor::new().with_defines(&Defines::new().with("SHADOWS"));
+        assert_eq!(
+            preprocessor.process("void main() {}\n").unwrap(),
+            "#define SHADOWS\n#line 1\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn defines_go_after_a_version_between_comments() {
+        let preprocessor = Preprocessor::new().with_defines(&Defines::new().with("SHADOWS"));
+        let source =
+            "// globe\n#version 450 core // needs storage blocks\n/* main */\nvoid main() {}\n";
+        assert_eq!(
+            preprocessor.process(source).unwrap(),
+            "// globe\n#version 450 core // needs storage blocks\n#define SHADOWS\n#line 3\n\
+             /* main */\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn defines_are_sorted_and_the_last_value_wins() {
+        let defines = Defines::new()
+            .with_value("LIGHTS", 2)
+            .with("SHADOWS")
+            .with_value("GAMMA", "2.2")
+            .with_value("LIGHTS", 4);
+        assert_eq!(defines.to_string(), "GAMMA=2.2, LIGHTS=4, SHADOWS");
+        assert_eq!(
+            Preprocessor::new()
+                .with_defines(&defines)
+                .process("#version 450\n")
+                .unwrap(),
+            "#version 450\n#define GAMMA 2.2\n#define LIGHTS 4\n#define SHADOWS\n#line 2\n"
+        );
+    }
+
+    #[test]
+    fn invalid_defines() {
+        for defines in &[
+            Defines::new().with("2D"),
+            Defines::new().with("A B"),
+            Defines::new().with_value("LIGHTS", "4\n#define X"),
+        ] {
+            match Preprocessor::new().with_defines(defines).process("") {
+                Err(Error::InvalidDefine { .. }) => (),
+                other => panic!("unexpected {:?} for {}", other, defines),
+            }
+        }
+    }
+
+    #[test]
+    fn line_numbers_follow_the_original_source() {
+        let preprocessor = Preprocessor::new()
+            .with_defines(&Defines::new().with("A").with("B"))
+            .with_vertex_inputs("Vertex", INPUTS);
+        let source = "#version 450\n\n#pragma vertex_inputs(Vertex)\nout vec4 color;\n";
+
+        // the defines and the inputs are followed by the number of the next original line
+        assert_eq!(
+            preprocessor.process(source).unwrap(),
+            format!(
+                "#version 450\n#define A\n#define B\n#line 2\n\n{}#line 4\nout vec4 color;\n",
+                INPUTS
+            )
+        );
+    }
+}
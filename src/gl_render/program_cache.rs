@@ -0,0 +1,200 @@
+use std::ffi::CStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use super::shader::{Program, ShaderSource};
+
+// every cache file starts with this, followed by the whole key, the little endian binary
+// format and the binary
+const MAGIC: &[u8; 4] = b"GLPB";
+
+// sha-256 of everything that goes into a program binary, the file name only uses the first 8
+// bytes so the whole key is stored in the entry and compared before the binary is handed to GL
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProgramKey([u8; 32]);
+
+// stores linked program binaries on disk so later runs can skip compiling and linking
+//
+// entries are keyed by the preprocessed sources of all stages and the driver strings, a
+// binary the driver rejects anyway (e.g. after a driver update that kept the version
+// string) is removed and the program is compiled from source again
+pub struct ProgramCache {
+    dir: PathBuf,
+    driver: String,
+    enabled: bool,
+}
+
+impl ProgramCache {
+    pub fn new(gl: &gl::Gl, dir: &Path) -> ProgramCache {
+        let driver = [gl::VENDOR, gl::RENDERER, gl::VERSION]
+            .iter()
+            .map(|&name| unsafe {
+                let string = gl.GetString(name);
+                if string.is_null() {
+                    String::new()
+                } else {
+                    CStr::from_ptr(string as *const _)
+                        .to_string_lossy()
+                        .into_owned()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        // drivers without any binary format can not give us anything to store
+        let mut formats: gl::types::GLint = 0;
+        unsafe {
+            gl.GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+        }
+
+        ProgramCache {
+            dir: dir.into(),
+            driver,
+            enabled: formats > 0,
+        }
+    }
+
+    pub fn key(&self, sources: &[ShaderSource], separable: bool) -> ProgramKey {
+        // the driver string is the only part without a terminator, so its length goes first
+        let mut hasher = Sha256::new();
+        hasher.update((self.driver.len() as u64).to_le_bytes());
+        hasher.update(self.driver.as_bytes());
+        hasher.update([separable as u8]);
+        for source in sources {
+            hasher.update(source.kind.to_le_bytes());
+            hasher.update(source.source.as_bytes_with_nul());
+        }
+
+        let mut key = [0; 32];
+        key.copy_from_slice(&hasher.finalize());
+        ProgramKey(key)
+    }
+
+    fn path(&self, key: ProgramKey) -> PathBuf {
+        let prefix = key.0[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        self.dir.join(format!("{}.bin", prefix))
+    }
+
+    // None if there is no entry, it belongs to another key or the driver rejected it
+    pub fn load(&self, gl: &gl::Gl, key: ProgramKey) -> Option<Program> {
+        if !self.enabled {
+            return None;
+        }
+
+        let path = self.path(key);
+        let contents = fs::read(&path).ok()?;
+        let program = parse_entry(&contents, key)
+            .and_then(|(format, binary)| Program::from_binary(gl, format, binary).ok());
+        if program.is_none() {
+            // the entry is stale, it gets replaced once the program is linked from source
+            let _ = fs::remove_file(&path);
+        }
+        program
+    }
+
+    pub fn store(&self, key: ProgramKey, program: &Program) -> io::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let (format, binary) = match program.binary() {
+            Some(binary) => binary,
+            None => return Ok(()),
+        };
+
+        let contents = entry_contents(key, format, &binary);
+        fs::create_dir_all(&self.dir)?;
+        // write to a temporary file first so a crash never leaves a truncated entry behind
+        let path = self.path(key);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, &contents)?;
+        fs::rename(&tmp_path, &path)
+    }
+}
+
+fn entry_contents(key: ProgramKey, format: gl::types::GLenum, binary: &[u8]) -> Vec<u8> {
+    let mut contents = Vec::with_capacity(MAGIC.len() + key.0.len() + 4 + binary.len());
+    contents.extend_from_slice(MAGIC);
+    contents.extend_from_slice(&key.0);
+    contents.extend_from_slice(&format.to_le_bytes());
+    contents.extend_from_slice(binary);
+    contents
+}
+
+// None if contents are not an entry or were stored for another key with the same file name
+fn parse_entry(contents: &[u8], key: ProgramKey) -> Option<(gl::types::GLenum, &[u8])> {
+    let rest = contents.strip_prefix(&MAGIC[..])?;
+    let rest = rest.strip_prefix(&key.0[..])?;
+    if rest.len() < 4 {
+        return None;
+    }
+    let (format, binary) = rest.split_at(4);
+    let format = u32::from_le_bytes([format[0], format[1], format[2], format[3]]);
+    Some((format, binary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gl_render::Preprocessor;
+
+    fn cache(driver: &str) -> ProgramCache {
+        ProgramCache {
+            dir: PathBuf::from("cache"),
+            driver: driver.into(),
+            enabled: true,
+        }
+    }
+
+    fn source(name: &str, text: &str) -> ShaderSource {
+        ShaderSource::from_str(name, text, &Preprocessor::new()).unwrap()
+    }
+
+    #[test]
+    fn entries_are_only_read_back_with_their_own_key() {
+        let sources = [source("a.vert", "void main() {}")];
+        let key = cache("driver").key(&sources, false);
+        let contents = entry_contents(key, 0x8fff, b"binary");
+        assert_eq!(parse_entry(&contents, key), Some((0x8fff, &b"binary"[..])));
+
+        // a key that ends up at the same path, like two colliding 64-bit hashes would
+        let mut colliding = key;
+        colliding.0[31] ^= 1;
+        assert_eq!(cache("driver").path(colliding), cache("driver").path(key));
+        assert_eq!(parse_entry(&contents, colliding), None);
+    }
+
+    #[test]
+    fn entries_without_the_whole_key_are_stale() {
+        let sources = [source("a.vert", "void main() {}")];
+        let key = cache("driver").key(&sources, false);
+
+        // the layout before the key was stored
+        let mut old = MAGIC.to_vec();
+        old.extend_from_slice(&0x8fffu32.to_le_bytes());
+        old.extend_from_slice(b"binary");
+        assert_eq!(parse_entry(&old, key), None);
+
+        let mut truncated = entry_contents(key, 0x8fff, b"");
+        truncated.truncate(MAGIC.len() + key.0.len() + 2);
+        assert_eq!(parse_entry(&truncated, key), None);
+    }
+
+    #[test]
+    fn key_covers_driver_separability_and_every_stage() {
+        let vert = || source("a.vert", "void main() {}");
+        let frag = || source("a.frag", "void main() {}");
+        let key = cache("driver").key(&[vert(), frag()], false);
+
+        assert_eq!(cache("driver").key(&[vert(), frag()], false), key);
+        assert_ne!(cache("other driver").key(&[vert(), frag()], false), key);
+        assert_ne!(cache("driver").key(&[vert(), frag()], true), key);
+        assert_ne!(cache("driver").key(&[vert()], false), key);
+        assert_ne!(cache("driver").key(&[frag(), vert()], false), key);
+    }
+}
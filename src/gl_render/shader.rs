@@ -1,48 +1,664 @@
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
+use std::fmt;
 
 use thiserror::Error;
 
 use nalgebra_glm as glm;
 
+use super::data::{self, GlslBaseType, VertexAttributeDesc};
+use super::diagnostics::{self, Diagnostic};
+use super::preprocessor::{self, Defines, Preprocessor};
+use super::program_cache::ProgramCache;
+use super::std430::{StorageBlock, StorageMember};
+use super::uniform::uniform_type_name;
 use crate::resources::{self, Resources};
 
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("failed to load resource {name}")]
+    #[error("failed to load resource {name}: {inner}")]
     ResourceLoad {
         name: String,
         inner: resources::Error,
     },
+    #[error("failed to preprocess shader {name}: {inner}")]
+    Preprocess {
+        name: String,
+        inner: preprocessor::Error,
+    },
     #[error("cannot determine shader type for resource {name}")]
     CanNotDetermineShaderTypeForResource { name: String },
-    #[error("failed to compile shader {name}: {message}")]
-    CompileError { name: String, message: String },
-    #[error("failed to link program {name}: {message}")]
-    LinkError { name: String, message: String },
+    #[error("shader source {name} contains a nul byte")]
+    SourceContainsNul { name: String },
+    #[error("failed to compile shader {name}{}:\n{message}", format_defines(.defines))]
+    CompileError {
+        name: String,
+        defines: Defines,
+        diagnostics: Vec<Diagnostic>,
+        // the diagnostics rendered with their source lines
+        message: String,
+    },
+    #[error("failed to link program {name}{}: {message}", format_defines(.defines))]
+    LinkError {
+        name: String,
+        defines: Defines,
+        message: String,
+    },
+    #[error("vertex layout does not match program inputs:{}", format_layout_problems(.problems))]
+    VertexLayoutMismatch { problems: Vec<VertexLayoutProblem> },
+    #[error("uniforms do not match program uniforms:{}", format_uniform_problems(.problems))]
+    UniformMismatch { problems: Vec<UniformProblem> },
+    #[allow(dead_code)]
+    #[error("program has no active shader storage block {name}")]
+    UnknownStorageBlock { name: String },
+    #[allow(dead_code)]
+    #[error("storage block {name} does not match the program:{}", format_storage_block_problems(.problems))]
+    StorageBlockMismatch {
+        name: String,
+        problems: Vec<StorageBlockProblem>,
+    },
+}
+
+fn format_storage_block_problems(problems: &[StorageBlockProblem]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!("\n  {}", problem))
+        .collect()
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StorageBlockProblem {
+    // a field has no active member in the block, possibly because it was optimized out
+    Missing {
+        name: String,
+        glsl_type: &'static str,
+    },
+    // the block has a member that no field provides
+    Extra {
+        name: String,
+        glsl_type: &'static str,
+    },
+    Mistyped {
+        name: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    Misplaced {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    WrongStride {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    WrongSize {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for StorageBlockProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageBlockProblem::Missing { name, glsl_type } => {
+                write!(f, "missing: no active member {} {}", glsl_type, name)
+            }
+            StorageBlockProblem::Extra { name, glsl_type } => {
+                write!(f, "extra: member {} {} has no field", glsl_type, name)
+            }
+            StorageBlockProblem::Mistyped {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "mistyped: member {} is {} but the field is {}",
+                name, expected, found
+            ),
+            StorageBlockProblem::Misplaced {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "misplaced: member {} is at offset {} but the field is at {}",
+                name, expected, found
+            ),
+            StorageBlockProblem::WrongStride {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "wrong stride: array {} has a stride of {} but the field has {}",
+                name, expected, found
+            ),
+            StorageBlockProblem::WrongSize { expected, found } => write!(
+                f,
+                "wrong size: block is {} bytes but the struct is {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+fn format_defines(defines: &Defines) -> String {
+    if defines.is_empty() {
+        String::new()
+    } else {
+        format!(" with {}", defines)
+    }
+}
+
+fn format_uniform_problems(problems: &[UniformProblem]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!("\n  {}", problem))
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UniformProblem {
+    // the program has no active uniform with the field's name, possibly because it was
+    // optimized out
+    Missing {
+        name: &'static str,
+        glsl_type: &'static str,
+    },
+    Mistyped {
+        name: &'static str,
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+impl fmt::Display for UniformProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UniformProblem::Missing { name, glsl_type } => {
+                write!(f, "missing: no active uniform {} {}", glsl_type, name)
+            }
+            UniformProblem::Mistyped {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "mistyped: uniform {} is {} but the field is {}",
+                name, expected, found
+            ),
+        }
+    }
+}
+
+fn format_layout_problems(problems: &[VertexLayoutProblem]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!("\n  {}", problem))
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VertexLayoutProblem {
+    // the program reads an input that no vertex field provides
+    Missing {
+        name: String,
+        location: usize,
+        glsl_type: String,
+    },
+    // a vertex field is not read by the program, possibly because it was optimized out
+    Extra {
+        field: &'static str,
+        location: usize,
+    },
+    Mistyped {
+        name: String,
+        field: &'static str,
+        location: usize,
+        expected: String,
+        found: String,
+    },
+    // the program reads an input of a type vertex fields cannot describe
+    Unsupported {
+        name: String,
+        location: usize,
+        gl_type: gl::types::GLenum,
+    },
+}
+
+impl fmt::Display for VertexLayoutProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VertexLayoutProblem::Missing {
+                name,
+                location,
+                glsl_type,
+            } => write!(
+                f,
+                "missing: input {} {} at location {} has no vertex field",
+                glsl_type, name, location
+            ),
+            VertexLayoutProblem::Extra { field, location } => write!(
+                f,
+                "extra: vertex field {} at location {} is not an active input",
+                field, location
+            ),
+            VertexLayoutProblem::Mistyped {
+                name,
+                field,
+                location,
+                expected,
+                found,
+            } => write!(
+                f,
+                "mistyped: input {} at location {} is {} but vertex field {} is {}",
+                name, location, expected, field, found
+            ),
+            VertexLayoutProblem::Unsupported {
+                name,
+                location,
+                gl_type,
+            } => write!(
+                f,
+                "unsupported: input {} at location {} has GL type {:#06x}",
+                name, location, gl_type
+            ),
+        }
+    }
+}
+
+// a vertex shader input as reported by the linked program
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActiveAttribute {
+    pub name: String,
+    pub location: usize,
+    pub gl_type: gl::types::GLenum,
+    // array length, 1 for non arrays
+    pub size: usize,
+}
+
+impl ActiveAttribute {
+    // None for types that are not vertex shader input types
+    pub fn glsl_type(&self) -> Option<&'static str> {
+        attribute_type_shape(self.gl_type)
+            .map(|(base, components, columns)| data::glsl_type_name(base, components, columns))
+    }
+
+    // a field can feed the input if the base type and columns match, it may have fewer
+    // components since GL fills in the rest with 0 and w with 1
+    pub fn accepts(&self, desc: &VertexAttributeDesc) -> bool {
+        match attribute_type_shape(self.gl_type) {
+            Some((base, components, columns)) => {
+                desc.glsl_base_type() == base
+                    && desc.locations == columns
+                    && desc.components <= components
+            }
+            None => false,
+        }
+    }
+}
+
+// a default block uniform as reported by the linked program
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActiveUniform {
+    pub name: String,
+    pub location: gl::types::GLint,
+    pub gl_type: gl::types::GLenum,
+    // array length, 1 for non arrays
+    pub size: usize,
+}
+
+// a shader storage block as reported by the linked program
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActiveStorageBlock {
+    pub name: String,
+    // minimum size, for blocks ending in a runtime-sized array drivers differ in whether
+    // one element is included
+    pub data_size: usize,
+    pub variables: Vec<ActiveBufferVariable>,
+}
+
+// a basic typed member of a shader storage block, members of structs are named like
+// `outer.inner` and arrays like `values[0]`
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActiveBufferVariable {
+    pub name: String,
+    pub gl_type: gl::types::GLenum,
+    pub offset: usize,
+    // 0 for non arrays
+    pub array_stride: usize,
+    // stride of the top level block member containing the variable, 0 if it is not an array
+    pub top_level_array_stride: usize,
+}
+
+// a buffer variable as the fields of a storage block struct expect it
+#[allow(dead_code)]
+struct ExpectedBufferVariable {
+    name: String,
+    gl_type: gl::types::GLenum,
+    offset: usize,
+    array_stride: usize,
+    // only checked for arrays of structs
+    top_level_array_stride: Option<usize>,
+}
+
+#[allow(dead_code)]
+fn expected_buffer_variables(
+    members: &[StorageMember],
+    prefix: &str,
+    base_offset: usize,
+    top_level_array_stride: Option<usize>,
+    variables: &mut Vec<ExpectedBufferVariable>,
+) {
+    let top_level = prefix.is_empty();
+    for member in members {
+        let is_array = member.array_len > 0 || member.runtime_sized;
+        let offset = base_offset + member.offset;
+
+        if member.members.is_empty() {
+            variables.push(ExpectedBufferVariable {
+                name: if is_array {
+                    format!("{}{}[0]", prefix, member.name)
+                } else {
+                    format!("{}{}", prefix, member.name)
+                },
+                gl_type: member.gl_type,
+                offset,
+                array_stride: if is_array { member.array_stride } else { 0 },
+                top_level_array_stride,
+            });
+        } else if is_array {
+            let top_level_array_stride = if top_level {
+                Some(member.array_stride)
+            } else {
+                top_level_array_stride
+            };
+            let prefix = format!("{}{}[0].", prefix, member.name);
+            expected_buffer_variables(
+                member.members,
+                &prefix,
+                offset,
+                top_level_array_stride,
+                variables,
+            );
+        } else {
+            let prefix = format!("{}{}.", prefix, member.name);
+            expected_buffer_variables(
+                member.members,
+                &prefix,
+                offset,
+                top_level_array_stride,
+                variables,
+            );
+        }
+    }
+}
+
+// drivers list every element of arrays of structs nested in a block member, only the first
+// one is compared
+#[allow(dead_code)]
+fn first_array_element_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(open) = rest.find('[') {
+        let close = match rest[open..].find(']') {
+            Some(close) => open + close,
+            None => break,
+        };
+        normalized.push_str(&rest[..open]);
+        normalized.push_str("[0]");
+        rest = &rest[close + 1..];
+    }
+    normalized.push_str(rest);
+    normalized
+}
+
+// base type, components per column and columns of an active attribute type
+fn attribute_type_shape(gl_type: gl::types::GLenum) -> Option<(GlslBaseType, usize, usize)> {
+    use GlslBaseType::*;
+    let shape = match gl_type {
+        gl::FLOAT => (Float, 1, 1),
+        gl::FLOAT_VEC2 => (Float, 2, 1),
+        gl::FLOAT_VEC3 => (Float, 3, 1),
+        gl::FLOAT_VEC4 => (Float, 4, 1),
+        gl::FLOAT_MAT2 => (Float, 2, 2),
+        gl::FLOAT_MAT3 => (Float, 3, 3),
+        gl::FLOAT_MAT4 => (Float, 4, 4),
+        gl::FLOAT_MAT2x3 => (Float, 3, 2),
+        gl::FLOAT_MAT2x4 => (Float, 4, 2),
+        gl::FLOAT_MAT3x2 => (Float, 2, 3),
+        gl::FLOAT_MAT3x4 => (Float, 4, 3),
+        gl::FLOAT_MAT4x2 => (Float, 2, 4),
+        gl::FLOAT_MAT4x3 => (Float, 3, 4),
+        gl::INT => (Int, 1, 1),
+        gl::INT_VEC2 => (Int, 2, 1),
+        gl::INT_VEC3 => (Int, 3, 1),
+        gl::INT_VEC4 => (Int, 4, 1),
+        gl::UNSIGNED_INT => (Uint, 1, 1),
+        gl::UNSIGNED_INT_VEC2 => (Uint, 2, 1),
+        gl::UNSIGNED_INT_VEC3 => (Uint, 3, 1),
+        gl::UNSIGNED_INT_VEC4 => (Uint, 4, 1),
+        gl::DOUBLE => (Double, 1, 1),
+        gl::DOUBLE_VEC2 => (Double, 2, 1),
+        gl::DOUBLE_VEC3 => (Double, 3, 1),
+        gl::DOUBLE_VEC4 => (Double, 4, 1),
+        gl::DOUBLE_MAT2 => (Double, 2, 2),
+        gl::DOUBLE_MAT3 => (Double, 3, 3),
+        gl::DOUBLE_MAT4 => (Double, 4, 4),
+        gl::DOUBLE_MAT2x3 => (Double, 3, 2),
+        gl::DOUBLE_MAT2x4 => (Double, 4, 2),
+        gl::DOUBLE_MAT3x2 => (Double, 2, 3),
+        gl::DOUBLE_MAT3x4 => (Double, 4, 3),
+        gl::DOUBLE_MAT4x2 => (Double, 2, 4),
+        gl::DOUBLE_MAT4x3 => (Double, 3, 4),
+        _ => return None,
+    };
+    Some(shape)
+}
+
+fn vertex_layout_problems(
+    attributes: &[ActiveAttribute],
+    layout: &[VertexAttributeDesc],
+) -> Vec<VertexLayoutProblem> {
+    let mut problems = Vec::new();
+
+    for attribute in attributes {
+        let glsl_type = match attribute.glsl_type() {
+            Some(glsl_type) => glsl_type,
+            None => {
+                problems.push(VertexLayoutProblem::Unsupported {
+                    name: attribute.name.clone(),
+                    location: attribute.location,
+                    gl_type: attribute.gl_type,
+                });
+                continue;
+            }
+        };
+
+        let desc = layout
+            .iter()
+            .find(|desc| desc.location == attribute.location);
+        match desc {
+            None => problems.push(VertexLayoutProblem::Missing {
+                name: attribute.name.clone(),
+                location: attribute.location,
+                glsl_type: glsl_type.to_string(),
+            }),
+            Some(desc) if !attribute.accepts(desc) => {
+                problems.push(VertexLayoutProblem::Mistyped {
+                    name: attribute.name.clone(),
+                    field: desc.name,
+                    location: attribute.location,
+                    expected: glsl_type.to_string(),
+                    found: desc.glsl_type().to_string(),
+                })
+            }
+            Some(_) => (),
+        }
+    }
+
+    for desc in layout {
+        if !attributes.iter().any(|a| a.location == desc.location) {
+            problems.push(VertexLayoutProblem::Extra {
+                field: desc.name,
+                location: desc.location,
+            });
+        }
+    }
+
+    problems
 }
 
 pub struct Program {
     id: gl::types::GLuint,
     gl: gl::Gl,
+    // the *_SHADER_BIT of every linked stage
+    stages: gl::types::GLbitfield,
+    // rendered warnings of compiling and linking, empty for programs loaded from binaries
+    warnings: Vec<String>,
 }
 
 impl Program {
-    pub fn from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<Program, Error> {
+    #[allow(dead_code)]
+    pub fn from_res(
+        gl: &gl::Gl,
+        res: &Resources,
+        name: &str,
+        defines: &Defines,
+    ) -> Result<Program, Error> {
+        Program::from_res_with(gl, res, name, &Preprocessor::new().with_defines(defines))
+    }
+
+    pub fn from_res_with(
+        gl: &gl::Gl,
+        res: &Resources,
+        name: &str,
+        preprocessor: &Preprocessor,
+    ) -> Result<Program, Error> {
+        Program::from_res_cached(gl, res, name, preprocessor, None)
+    }
+
+    // like from_res_with, but reuses a binary from the cache if the preprocessed sources
+    // have been linked before
+    pub fn from_res_cached(
+        gl: &gl::Gl,
+        res: &Resources,
+        name: &str,
+        preprocessor: &Preprocessor,
+        cache: Option<&ProgramCache>,
+    ) -> Result<Program, Error> {
+        let sources = Program::sources_from_res(res, name, preprocessor)?;
+        Program::from_sources(gl, name, &sources, preprocessor.defines(), cache, false)
+    }
+
+    // the preprocessed stages of the program from_res_cached would link, does not need GL so
+    // it can run on any thread
+    pub fn sources_from_res(
+        res: &Resources,
+        name: &str,
+        preprocessor: &Preprocessor,
+    ) -> Result<Vec<ShaderSource>, Error> {
         const POSSIBLE_EXT: [&str; 2] = [".vert", ".frag"];
 
-        let shaders = POSSIBLE_EXT
+        POSSIBLE_EXT
+            .iter()
+            .map(|file_extension| {
+                let name = format!("{}{}", name, file_extension);
+                ShaderSource::from_res(res, &name, preprocessor)
+            })
+            .collect()
+    }
+
+    // links a single stage like "shaders/globe.vert" into a separable program for use in a
+    // ProgramPipeline, vertex stages have to redeclare the gl_PerVertex block they write
+    #[allow(dead_code)]
+    pub fn from_res_separable(
+        gl: &gl::Gl,
+        res: &Resources,
+        name: &str,
+        preprocessor: &Preprocessor,
+        cache: Option<&ProgramCache>,
+    ) -> Result<Program, Error> {
+        let sources = [ShaderSource::from_res(res, name, preprocessor)?];
+        Program::from_sources(gl, name, &sources, preprocessor.defines(), cache, true)
+    }
+
+    // links already loaded sources, name is used for errors
+    pub fn from_sources(
+        gl: &gl::Gl,
+        name: &str,
+        sources: &[ShaderSource],
+        defines: &Defines,
+        cache: Option<&ProgramCache>,
+        separable: bool,
+    ) -> Result<Program, Error> {
+        let stages = sources
+            .iter()
+            .fold(0, |stages, source| stages | stage_bit(source.kind));
+
+        let key = cache.map(|cache| cache.key(sources, separable));
+        if let (Some(cache), Some(key)) = (cache, key) {
+            if let Some(mut program) = cache.load(gl, key) {
+                program.stages = stages;
+                return Ok(program);
+            }
+        }
+
+        let shaders = sources
             .iter()
-            .map(|file_extension| Shader::from_res(gl, res, &format!("{}{}", name, file_extension)))
+            .map(|source| source.compile(gl))
             .collect::<Result<Vec<Shader>, Error>>()?;
 
-        Program::from_shaders(gl, &shaders[..]).map_err(|message| Error::LinkError {
-            name: name.into(),
-            message,
-        })
+        let mut program =
+            Program::link(gl, &shaders[..], separable).map_err(|message| Error::LinkError {
+                name: name.into(),
+                defines: defines.clone(),
+                message,
+            })?;
+
+        let mut warnings: Vec<String> = sources
+            .iter()
+            .zip(&shaders)
+            .filter(|(_, shader)| !shader.diagnostics().is_empty())
+            .map(|(source, shader)| source.render(shader.diagnostics()))
+            .collect();
+        warnings.append(&mut program.warnings);
+        program.warnings = warnings;
+
+        if let (Some(cache), Some(key)) = (cache, key) {
+            // a failed write only means compiling again next time
+            let _ = cache.store(key, &program);
+        }
+
+        Ok(program)
     }
 
+    #[allow(dead_code)]
     pub fn from_shaders(gl: &gl::Gl, shaders: &[Shader]) -> Result<Program, String> {
+        Program::link(gl, shaders, false)
+    }
+
+    // a program whose stages can be combined with stages of other programs in a
+    // ProgramPipeline
+    #[allow(dead_code)]
+    pub fn from_shaders_separable(gl: &gl::Gl, shaders: &[Shader]) -> Result<Program, String> {
+        Program::link(gl, shaders, true)
+    }
+
+    fn link(gl: &gl::Gl, shaders: &[Shader], separable: bool) -> Result<Program, String> {
         let id = unsafe { gl.CreateProgram() };
+        unsafe {
+            // lets ProgramCache retrieve the linked binary
+            gl.ProgramParameteri(id, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as _);
+            if separable {
+                gl.ProgramParameteri(id, gl::PROGRAM_SEPARABLE, gl::TRUE as _);
+            }
+        }
         for shader in shaders {
             unsafe {
                 gl.AttachShader(id, shader.id());
@@ -52,28 +668,20 @@ impl Program {
         unsafe { gl.LinkProgram(id) };
 
         let mut success: gl::types::GLint = 1;
+        let mut len: gl::types::GLint = 0;
         unsafe {
             gl.GetProgramiv(id, gl::LINK_STATUS, &mut success);
+            gl.GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut len);
         }
+        let log = read_info_log(len, |size, written, log| unsafe {
+            gl.GetProgramInfoLog(id, size, written, log);
+        });
 
         if success == 0 {
-            let mut len: gl::types::GLint = 0;
             unsafe {
-                gl.GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut len);
+                gl.DeleteProgram(id);
             }
-
-            let error = create_cstring_with_len(len as usize);
-
-            unsafe {
-                gl.GetProgramInfoLog(
-                    id,
-                    len,
-                    std::ptr::null_mut(),
-                    error.as_ptr() as *mut gl::types::GLchar,
-                );
-            }
-
-            return Err(error.to_string_lossy().into_owned());
+            return Err(log);
         }
 
         for shader in shaders {
@@ -82,7 +690,100 @@ impl Program {
             }
         }
 
-        Ok(Program { id, gl: gl.clone() })
+        let mut warnings = Vec::new();
+        if !log.is_empty() {
+            warnings.push(log);
+        }
+
+        Ok(Program {
+            id,
+            gl: gl.clone(),
+            stages: shaders
+                .iter()
+                .fold(0, |stages, shader| stages | stage_bit(shader.kind())),
+            warnings,
+        })
+    }
+
+    // links a program from the output of binary(), fails if the driver no longer accepts it
+    pub fn from_binary(
+        gl: &gl::Gl,
+        format: gl::types::GLenum,
+        binary: &[u8],
+    ) -> Result<Program, String> {
+        let id = unsafe { gl.CreateProgram() };
+        let program = Program {
+            id,
+            gl: gl.clone(),
+            stages: 0,
+            warnings: Vec::new(),
+        };
+
+        let mut success: gl::types::GLint = 0;
+        unsafe {
+            gl.ProgramBinary(
+                id,
+                format,
+                binary.as_ptr() as *const gl::types::GLvoid,
+                binary.len() as gl::types::GLsizei,
+            );
+            gl.GetProgramiv(id, gl::LINK_STATUS, &mut success);
+        }
+
+        if success == 0 {
+            return Err("program binary was rejected by the driver".into());
+        }
+
+        Ok(program)
+    }
+
+    // the driver specific binary format and the linked program binary
+    pub fn binary(&self) -> Option<(gl::types::GLenum, Vec<u8>)> {
+        let mut len: gl::types::GLint = 0;
+        unsafe {
+            self.gl
+                .GetProgramiv(self.id, gl::PROGRAM_BINARY_LENGTH, &mut len);
+        }
+        if len <= 0 {
+            return None;
+        }
+
+        let mut binary: Vec<u8> = vec![0; len as usize];
+        let mut written: gl::types::GLsizei = 0;
+        let mut format: gl::types::GLenum = 0;
+        unsafe {
+            self.gl.GetProgramBinary(
+                self.id,
+                len,
+                &mut written,
+                &mut format,
+                binary.as_mut_ptr() as *mut gl::types::GLvoid,
+            );
+        }
+        binary.truncate(written as usize);
+
+        Some((format, binary))
+    }
+
+    // the stages a ProgramPipeline takes from this program, 0 if unknown because the
+    // program was loaded with from_binary
+    pub fn stages(&self) -> gl::types::GLbitfield {
+        self.stages
+    }
+
+    #[allow(dead_code)]
+    pub fn is_separable(&self) -> bool {
+        let mut separable: gl::types::GLint = 0;
+        unsafe {
+            self.gl
+                .GetProgramiv(self.id, gl::PROGRAM_SEPARABLE, &mut separable);
+        }
+        separable != 0
+    }
+
+    // compiler and linker messages of a program that was built successfully
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
     }
 
     pub fn set_used(&self) {
@@ -102,6 +803,64 @@ impl Program {
         self.id
     }
 
+    pub fn active_attributes(&self) -> Vec<ActiveAttribute> {
+        let mut count: gl::types::GLint = 0;
+        let mut max_len: gl::types::GLint = 0;
+        unsafe {
+            self.gl
+                .GetProgramiv(self.id, gl::ACTIVE_ATTRIBUTES, &mut count);
+            self.gl
+                .GetProgramiv(self.id, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_len);
+        }
+
+        let mut attributes = Vec::with_capacity(count as usize);
+        for index in 0..count as gl::types::GLuint {
+            let mut name: Vec<u8> = vec![0; max_len as usize + 1];
+            let mut len: gl::types::GLsizei = 0;
+            let mut size: gl::types::GLint = 0;
+            let mut gl_type: gl::types::GLenum = 0;
+            let location = unsafe {
+                self.gl.GetActiveAttrib(
+                    self.id,
+                    index,
+                    name.len() as gl::types::GLsizei,
+                    &mut len,
+                    &mut size,
+                    &mut gl_type,
+                    name.as_mut_ptr() as *mut gl::types::GLchar,
+                );
+                name.truncate(len as usize);
+                let name = CString::new(name.clone()).unwrap();
+                self.gl.GetAttribLocation(self.id, name.as_ptr())
+            };
+
+            // built-in inputs such as gl_VertexID have no location
+            if location < 0 {
+                continue;
+            }
+
+            attributes.push(ActiveAttribute {
+                name: String::from_utf8_lossy(&name).into_owned(),
+                location: location as usize,
+                gl_type,
+                size: size as usize,
+            });
+        }
+
+        attributes
+    }
+
+    // compares a vertex layout such as the LAYOUT generated by the vertex derive with the
+    // active inputs of the linked program
+    pub fn check_vertex_layout(&self, layout: &[VertexAttributeDesc]) -> Result<(), Error> {
+        let problems = vertex_layout_problems(&self.active_attributes(), layout);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::VertexLayoutMismatch { problems })
+        }
+    }
+
     pub fn get_uniform_location(&self, name: &str) -> gl::types::GLint {
         unsafe {
             self.gl
@@ -111,8 +870,13 @@ impl Program {
 
     pub fn set_matrix4fv(&self, loc: gl::types::GLint, mat: &glm::Mat4) {
         unsafe {
-            self.gl
-                .UniformMatrix4fv(loc, 1, gl::FALSE, glm::value_ptr(&mat).as_ptr());
+            self.gl.ProgramUniformMatrix4fv(
+                self.id,
+                loc,
+                1,
+                gl::FALSE,
+                glm::value_ptr(mat).as_ptr(),
+            );
         }
     }
 
@@ -123,13 +887,295 @@ impl Program {
 
     pub fn set_1i(&self, loc: gl::types::GLint, int: gl::types::GLint) {
         unsafe {
-            self.gl.Uniform1i(loc, int);
+            self.gl.ProgramUniform1i(self.id, loc, int);
         }
     }
 
+    #[allow(dead_code)]
     pub fn get_and_set_1i(&self, name: &str, int: gl::types::GLint) {
         self.set_1i(self.get_uniform_location(name), int);
     }
+
+    pub fn set_1ui(&self, loc: gl::types::GLint, uint: gl::types::GLuint) {
+        unsafe {
+            self.gl.ProgramUniform1ui(self.id, loc, uint);
+        }
+    }
+
+    pub fn set_1f(&self, loc: gl::types::GLint, float: f32) {
+        unsafe {
+            self.gl.ProgramUniform1f(self.id, loc, float);
+        }
+    }
+
+    pub fn set_2fv(&self, loc: gl::types::GLint, vec: &glm::Vec2) {
+        unsafe {
+            self.gl
+                .ProgramUniform2fv(self.id, loc, 1, glm::value_ptr(vec).as_ptr());
+        }
+    }
+
+    pub fn set_3fv(&self, loc: gl::types::GLint, vec: &glm::Vec3) {
+        unsafe {
+            self.gl
+                .ProgramUniform3fv(self.id, loc, 1, glm::value_ptr(vec).as_ptr());
+        }
+    }
+
+    pub fn set_4fv(&self, loc: gl::types::GLint, vec: &glm::Vec4) {
+        unsafe {
+            self.gl
+                .ProgramUniform4fv(self.id, loc, 1, glm::value_ptr(vec).as_ptr());
+        }
+    }
+
+    pub fn set_matrix3fv(&self, loc: gl::types::GLint, mat: &glm::Mat3) {
+        unsafe {
+            self.gl.ProgramUniformMatrix3fv(
+                self.id,
+                loc,
+                1,
+                gl::FALSE,
+                glm::value_ptr(mat).as_ptr(),
+            );
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn active_storage_block(&self, name: &str) -> Option<ActiveStorageBlock> {
+        let c_name = CString::new(name).ok()?;
+        let index = unsafe {
+            self.gl
+                .GetProgramResourceIndex(self.id, gl::SHADER_STORAGE_BLOCK, c_name.as_ptr())
+        };
+        if index == gl::INVALID_INDEX {
+            return None;
+        }
+
+        let block_props = [gl::BUFFER_DATA_SIZE, gl::NUM_ACTIVE_VARIABLES];
+        let mut block_values: [gl::types::GLint; 2] = [0; 2];
+        unsafe {
+            self.gl.GetProgramResourceiv(
+                self.id,
+                gl::SHADER_STORAGE_BLOCK,
+                index,
+                block_props.len() as gl::types::GLsizei,
+                block_props.as_ptr(),
+                block_values.len() as gl::types::GLsizei,
+                std::ptr::null_mut(),
+                block_values.as_mut_ptr(),
+            );
+        }
+
+        let mut indices: Vec<gl::types::GLint> = vec![0; block_values[1] as usize];
+        unsafe {
+            self.gl.GetProgramResourceiv(
+                self.id,
+                gl::SHADER_STORAGE_BLOCK,
+                index,
+                1,
+                &gl::ACTIVE_VARIABLES,
+                indices.len() as gl::types::GLsizei,
+                std::ptr::null_mut(),
+                indices.as_mut_ptr(),
+            );
+        }
+
+        let variable_props = [
+            gl::NAME_LENGTH,
+            gl::TYPE,
+            gl::OFFSET,
+            gl::ARRAY_STRIDE,
+            gl::TOP_LEVEL_ARRAY_STRIDE,
+        ];
+        let block_prefix = format!("{}.", name);
+        let mut variables = Vec::with_capacity(indices.len());
+        for variable_index in indices {
+            let variable_index = variable_index as gl::types::GLuint;
+            let mut values: [gl::types::GLint; 5] = [0; 5];
+            unsafe {
+                self.gl.GetProgramResourceiv(
+                    self.id,
+                    gl::BUFFER_VARIABLE,
+                    variable_index,
+                    variable_props.len() as gl::types::GLsizei,
+                    variable_props.as_ptr(),
+                    values.len() as gl::types::GLsizei,
+                    std::ptr::null_mut(),
+                    values.as_mut_ptr(),
+                );
+            }
+
+            let mut variable_name: Vec<u8> = vec![0; values[0] as usize + 1];
+            let mut len: gl::types::GLsizei = 0;
+            unsafe {
+                self.gl.GetProgramResourceName(
+                    self.id,
+                    gl::BUFFER_VARIABLE,
+                    variable_index,
+                    variable_name.len() as gl::types::GLsizei,
+                    &mut len,
+                    variable_name.as_mut_ptr() as *mut gl::types::GLchar,
+                );
+            }
+            variable_name.truncate(len as usize);
+            let variable_name = String::from_utf8_lossy(&variable_name);
+
+            // members of blocks with an instance name are prefixed with the block name
+            variables.push(ActiveBufferVariable {
+                name: variable_name
+                    .strip_prefix(&block_prefix)
+                    .unwrap_or(&variable_name)
+                    .to_string(),
+                gl_type: values[1] as gl::types::GLenum,
+                offset: values[2] as usize,
+                array_stride: values[3] as usize,
+                top_level_array_stride: values[4] as usize,
+            });
+        }
+
+        Some(ActiveStorageBlock {
+            name: name.into(),
+            data_size: block_values[0] as usize,
+            variables,
+        })
+    }
+
+    // compares a struct deriving StorageBlock with the members of the named shader storage
+    // block of the linked program
+    #[allow(dead_code)]
+    pub fn check_storage_block<S>(&self, name: &str) -> Result<(), Error>
+    where
+        S: StorageBlock,
+    {
+        let block = self
+            .active_storage_block(name)
+            .ok_or_else(|| Error::UnknownStorageBlock { name: name.into() })?;
+
+        let mut expected = Vec::new();
+        expected_buffer_variables(S::MEMBERS, "", 0, None, &mut expected);
+
+        let mut problems = Vec::new();
+        for field in &expected {
+            let variable = match block.variables.iter().find(|v| v.name == field.name) {
+                Some(variable) => variable,
+                None => {
+                    problems.push(StorageBlockProblem::Missing {
+                        name: field.name.clone(),
+                        glsl_type: uniform_type_name(field.gl_type),
+                    });
+                    continue;
+                }
+            };
+
+            if variable.gl_type != field.gl_type {
+                problems.push(StorageBlockProblem::Mistyped {
+                    name: field.name.clone(),
+                    expected: uniform_type_name(variable.gl_type),
+                    found: uniform_type_name(field.gl_type),
+                });
+            }
+            if variable.offset != field.offset {
+                problems.push(StorageBlockProblem::Misplaced {
+                    name: field.name.clone(),
+                    expected: variable.offset,
+                    found: field.offset,
+                });
+            }
+            if variable.array_stride != field.array_stride {
+                problems.push(StorageBlockProblem::WrongStride {
+                    name: field.name.clone(),
+                    expected: variable.array_stride,
+                    found: field.array_stride,
+                });
+            }
+            match field.top_level_array_stride {
+                Some(stride) if stride != variable.top_level_array_stride => {
+                    problems.push(StorageBlockProblem::WrongStride {
+                        name: field.name.clone(),
+                        expected: variable.top_level_array_stride,
+                        found: stride,
+                    })
+                }
+                _ => (),
+            }
+        }
+
+        for variable in &block.variables {
+            let name = first_array_element_name(&variable.name);
+            if !expected.iter().any(|field| field.name == name) {
+                problems.push(StorageBlockProblem::Extra {
+                    name: variable.name.clone(),
+                    glsl_type: uniform_type_name(variable.gl_type),
+                });
+            }
+        }
+
+        // a runtime-sized array makes the reported size driver dependent
+        let runtime_sized = S::MEMBERS.last().is_some_and(|m| m.runtime_sized);
+        if !runtime_sized && block.data_size != std::mem::size_of::<S>() {
+            problems.push(StorageBlockProblem::WrongSize {
+                expected: block.data_size,
+                found: std::mem::size_of::<S>(),
+            });
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::StorageBlockMismatch {
+                name: name.into(),
+                problems,
+            })
+        }
+    }
+
+    pub fn active_uniforms(&self) -> Vec<ActiveUniform> {
+        let mut count: gl::types::GLint = 0;
+        let mut max_len: gl::types::GLint = 0;
+        unsafe {
+            self.gl
+                .GetProgramiv(self.id, gl::ACTIVE_UNIFORMS, &mut count);
+            self.gl
+                .GetProgramiv(self.id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_len);
+        }
+
+        let mut uniforms = Vec::with_capacity(count as usize);
+        for index in 0..count as gl::types::GLuint {
+            let mut name: Vec<u8> = vec![0; max_len as usize + 1];
+            let mut len: gl::types::GLsizei = 0;
+            let mut size: gl::types::GLint = 0;
+            let mut gl_type: gl::types::GLenum = 0;
+            unsafe {
+                self.gl.GetActiveUniform(
+                    self.id,
+                    index,
+                    name.len() as gl::types::GLsizei,
+                    &mut len,
+                    &mut size,
+                    &mut gl_type,
+                    name.as_mut_ptr() as *mut gl::types::GLchar,
+                );
+            }
+            name.truncate(len as usize);
+            let name = String::from_utf8_lossy(&name).into_owned();
+
+            // uniforms inside blocks have no location and are set through buffers
+            let location = self.get_uniform_location(&name);
+            if location < 0 {
+                continue;
+            }
+
+            uniforms.push(ActiveUniform {
+                name,
+                location,
+                gl_type,
+                size: size as usize,
+            });
+        }
+
+        uniforms
+    }
 }
 
 impl Drop for Program {
@@ -140,46 +1186,161 @@ impl Drop for Program {
     }
 }
 
-pub struct Shader {
-    id: gl::types::GLuint,
-    gl: gl::Gl,
+// the preprocessed source of one stage, ready to be compiled
+pub struct ShaderSource {
+    pub name: String,
+    pub kind: gl::types::GLenum,
+    pub defines: Defines,
+    pub source: CString,
+    // the source before preprocessing, which the #line directives of the preprocessor keep
+    // driver line numbers pointing into
+    pub original: String,
 }
 
-impl Shader {
-    pub fn from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<Shader, Error> {
+impl ShaderSource {
+    pub fn from_res(
+        res: &Resources,
+        name: &str,
+        preprocessor: &Preprocessor,
+    ) -> Result<ShaderSource, Error> {
+        let source = res.load_cstring(name).map_err(|e| Error::ResourceLoad {
+            name: name.into(),
+            inner: e,
+        })?;
+
+        ShaderSource::from_str(name, &source.to_string_lossy(), preprocessor)
+    }
+
+    // the stage is determined by the extension of name like for resources
+    pub fn from_str(
+        name: &str,
+        source: &str,
+        preprocessor: &Preprocessor,
+    ) -> Result<ShaderSource, Error> {
         const POSSIBLE_EXT: [(&str, gl::types::GLenum); 2] =
             [(".vert", gl::VERTEX_SHADER), (".frag", gl::FRAGMENT_SHADER)];
 
-        let shader_kind = POSSIBLE_EXT
+        let kind = POSSIBLE_EXT
             .iter()
             .find(|&&(file_extension, _)| name.ends_with(file_extension))
             .map(|&(_, kind)| kind)
             .ok_or_else(|| Error::CanNotDetermineShaderTypeForResource { name: name.into() })?;
 
-        let source = res.load_cstring(name).map_err(|e| Error::ResourceLoad {
-            name: name.into(),
-            inner: e,
-        })?;
+        let original = source.to_string();
+        let source = preprocessor
+            .process(&original)
+            .map_err(|e| Error::Preprocess {
+                name: name.into(),
+                inner: e,
+            })?;
+        let source =
+            CString::new(source).map_err(|_| Error::SourceContainsNul { name: name.into() })?;
 
-        Shader::from_source(gl, &source, shader_kind).map_err(|message| Error::CompileError {
+        Ok(ShaderSource {
             name: name.into(),
-            message,
+            kind,
+            defines: preprocessor.defines().clone(),
+            source,
+            original,
+        })
+    }
+
+    pub fn compile(&self, gl: &gl::Gl) -> Result<Shader, Error> {
+        Shader::from_source(gl, &self.source, self.kind).map_err(|diagnostics| {
+            Error::CompileError {
+                name: self.name.clone(),
+                defines: self.defines.clone(),
+                message: self.render(&diagnostics),
+                diagnostics,
+            }
         })
     }
 
-    pub fn _from_vert_source(gl: &gl::Gl, source: &CStr) -> Result<Shader, String> {
+    pub fn render(&self, diagnostics: &[Diagnostic]) -> String {
+        diagnostics::render(&self.name, &self.original, diagnostics)
+    }
+}
+
+// compiles variants of one program on demand and keeps them, keyed by their defines
+pub struct ProgramVariants {
+    name: String,
+    preprocessor: Preprocessor,
+    variants: HashMap<Defines, Program>,
+}
+
+#[allow(dead_code)]
+impl ProgramVariants {
+    // the preprocessor provides everything but the defines, which differ per variant
+    pub fn new(name: &str, preprocessor: Preprocessor) -> ProgramVariants {
+        ProgramVariants {
+            name: name.into(),
+            preprocessor,
+            variants: HashMap::new(),
+        }
+    }
+
+    pub fn get(
+        &mut self,
+        gl: &gl::Gl,
+        res: &Resources,
+        defines: &Defines,
+        cache: Option<&ProgramCache>,
+    ) -> Result<&Program, Error> {
+        if !self.variants.contains_key(defines) {
+            let preprocessor = self.preprocessor.clone().with_defines(defines);
+            let program = Program::from_res_cached(gl, res, &self.name, &preprocessor, cache)?;
+            self.variants.insert(defines.clone(), program);
+        }
+        Ok(&self.variants[defines])
+    }
+
+    // the variants compiled so far
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+}
+
+pub struct Shader {
+    id: gl::types::GLuint,
+    gl: gl::Gl,
+    kind: gl::types::GLenum,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Shader {
+    #[allow(dead_code)]
+    pub fn from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<Shader, Error> {
+        Shader::from_res_with(gl, res, name, &Preprocessor::new())
+    }
+
+    #[allow(dead_code)]
+    pub fn from_res_with(
+        gl: &gl::Gl,
+        res: &Resources,
+        name: &str,
+        preprocessor: &Preprocessor,
+    ) -> Result<Shader, Error> {
+        ShaderSource::from_res(res, name, preprocessor)?.compile(gl)
+    }
+
+    pub fn _from_vert_source(gl: &gl::Gl, source: &CStr) -> Result<Shader, Vec<Diagnostic>> {
         Shader::from_source(gl, source, gl::VERTEX_SHADER)
     }
 
-    pub fn _from_frag_source(gl: &gl::Gl, source: &CStr) -> Result<Shader, String> {
+    pub fn _from_frag_source(gl: &gl::Gl, source: &CStr) -> Result<Shader, Vec<Diagnostic>> {
         Shader::from_source(gl, source, gl::FRAGMENT_SHADER)
     }
 
+    // fails with the parsed info log, on success the log is kept in diagnostics()
     pub fn from_source(
         gl: &gl::Gl,
         source: &CStr,
         kind: gl::types::GLenum,
-    ) -> Result<Shader, String> {
+    ) -> Result<Shader, Vec<Diagnostic>> {
         let id = unsafe { gl.CreateShader(kind) };
 
         unsafe {
@@ -188,35 +1349,41 @@ impl Shader {
         }
 
         let mut success: gl::types::GLint = 1;
+        let mut len: gl::types::GLint = 0;
         unsafe {
             gl.GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
+            gl.GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
         }
+        let log = read_info_log(len, |size, written, log| unsafe {
+            gl.GetShaderInfoLog(id, size, written, log);
+        });
+        let diagnostics = diagnostics::parse_info_log(&log);
 
+        let shader = Shader {
+            id,
+            gl: gl.clone(),
+            kind,
+            diagnostics,
+        };
         if success == 0 {
-            let mut len: gl::types::GLint = 0;
-            unsafe {
-                gl.GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
-            }
-
-            let error: CString = create_cstring_with_len(len as usize);
-            unsafe {
-                gl.GetShaderInfoLog(
-                    id,
-                    len,
-                    std::ptr::null_mut(),
-                    error.as_ptr() as *mut gl::types::GLchar,
-                );
-            }
-
-            return Err(error.to_string_lossy().into_owned());
+            return Err(shader.diagnostics.clone());
         }
 
-        Ok(Shader { id, gl: gl.clone() })
+        Ok(shader)
+    }
+
+    // warnings and notes of a successful compile
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
     }
 
     pub fn id(&self) -> gl::types::GLuint {
         self.id
     }
+
+    pub fn kind(&self) -> gl::types::GLenum {
+        self.kind
+    }
 }
 
 impl Drop for Shader {
@@ -227,8 +1394,178 @@ impl Drop for Shader {
     }
 }
 
-fn create_cstring_with_len(len: usize) -> CString {
-    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
-    buffer.extend(std::iter::repeat(b' ').take(len));
-    unsafe { CString::from_vec_unchecked(buffer) }
+// the glUseProgramStages bit for a shader kind
+fn stage_bit(kind: gl::types::GLenum) -> gl::types::GLbitfield {
+    match kind {
+        gl::VERTEX_SHADER => gl::VERTEX_SHADER_BIT,
+        gl::TESS_CONTROL_SHADER => gl::TESS_CONTROL_SHADER_BIT,
+        gl::TESS_EVALUATION_SHADER => gl::TESS_EVALUATION_SHADER_BIT,
+        gl::GEOMETRY_SHADER => gl::GEOMETRY_SHADER_BIT,
+        gl::FRAGMENT_SHADER => gl::FRAGMENT_SHADER_BIT,
+        gl::COMPUTE_SHADER => gl::COMPUTE_SHADER_BIT,
+        _ => 0,
+    }
+}
+
+// reads an info log of len bytes including the nul, without the nul and trailing newlines
+fn read_info_log<F>(len: gl::types::GLint, read: F) -> String
+where
+    F: FnOnce(gl::types::GLsizei, *mut gl::types::GLsizei, *mut gl::types::GLchar),
+{
+    if len <= 1 {
+        return String::new();
+    }
+
+    let mut buffer: Vec<u8> = vec![0; len as usize];
+    let mut written: gl::types::GLsizei = 0;
+    read(
+        len,
+        &mut written,
+        buffer.as_mut_ptr() as *mut gl::types::GLchar,
+    );
+    buffer.truncate(written.max(0) as usize);
+
+    String::from_utf8_lossy(&buffer).trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gl_render::data::AttributePath;
+
+    fn desc(
+        name: &'static str,
+        location: usize,
+        components: usize,
+        gl_type: gl::types::GLenum,
+        path: AttributePath,
+    ) -> VertexAttributeDesc {
+        VertexAttributeDesc {
+            name,
+            location,
+            locations: 1,
+            components,
+            gl_type,
+            normalized: false,
+            path,
+            offset: 0,
+        }
+    }
+
+    fn input(name: &str, location: usize, gl_type: gl::types::GLenum) -> ActiveAttribute {
+        ActiveAttribute {
+            name: name.into(),
+            location,
+            gl_type,
+            size: 1,
+        }
+    }
+
+    #[test]
+    fn fewer_components_than_the_input_are_accepted() {
+        let layout = [desc("pos", 0, 3, gl::FLOAT, AttributePath::Float)];
+        let inputs = [input("pos", 0, gl::FLOAT_VEC4)];
+        assert_eq!(vertex_layout_problems(&inputs, &layout), vec![]);
+    }
+
+    #[test]
+    fn more_components_than_the_input_are_mistyped() {
+        let layout = [desc("pos", 0, 4, gl::FLOAT, AttributePath::Float)];
+        let inputs = [input("pos", 0, gl::FLOAT_VEC2)];
+        assert_eq!(
+            vertex_layout_problems(&inputs, &layout),
+            vec![VertexLayoutProblem::Mistyped {
+                name: "pos".into(),
+                field: "pos",
+                location: 0,
+                expected: "vec2".into(),
+                found: "vec4".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn base_types_must_match() {
+        let layout = [
+            desc("id", 0, 1, gl::INT, AttributePath::Integer),
+            desc("flags", 1, 1, gl::UNSIGNED_INT, AttributePath::Integer),
+            desc("weight", 2, 1, gl::FLOAT, AttributePath::Float),
+        ];
+        let inputs = [
+            input("id", 0, gl::INT),
+            input("flags", 1, gl::INT),
+            input("weight", 2, gl::DOUBLE),
+        ];
+        let problems = vertex_layout_problems(&inputs, &layout);
+        assert_eq!(problems.len(), 2);
+        assert!(problems
+            .iter()
+            .all(|problem| matches!(problem, VertexLayoutProblem::Mistyped { .. })));
+    }
+
+    #[test]
+    fn unknown_input_types_are_reported() {
+        let layout = [desc("pos", 0, 3, gl::FLOAT, AttributePath::Float)];
+        let inputs = [input("pos", 0, gl::SAMPLER_2D)];
+        assert_eq!(
+            vertex_layout_problems(&inputs, &layout),
+            vec![VertexLayoutProblem::Unsupported {
+                name: "pos".into(),
+                location: 0,
+                gl_type: gl::SAMPLER_2D,
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_and_extra_fields() {
+        let layout = [desc("color", 1, 4, gl::FLOAT, AttributePath::Float)];
+        let inputs = [input("pos", 0, gl::FLOAT_VEC3)];
+        assert_eq!(
+            vertex_layout_problems(&inputs, &layout),
+            vec![
+                VertexLayoutProblem::Missing {
+                    name: "pos".into(),
+                    location: 0,
+                    glsl_type: "vec3".into(),
+                },
+                VertexLayoutProblem::Extra {
+                    field: "color",
+                    location: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn nul_bytes_in_sources_are_reported_as_such() {
+        match ShaderSource::from_str("a.vert", "void main() {}\0", &Preprocessor::new()) {
+            Err(Error::SourceContainsNul { name }) => assert_eq!(name, "a.vert"),
+            Err(other) => panic!("unexpected {:?}", other),
+            Ok(_) => panic!("a source with a nul byte was accepted"),
+        }
+    }
+
+    #[test]
+    fn variant_keys_do_not_depend_on_the_order_of_defines() {
+        let ab = Defines::new().with("SHADOWS").with_value("LIGHTS", 4);
+        let ba = Defines::new().with_value("LIGHTS", 4).with("SHADOWS");
+        let other_value = Defines::new().with("SHADOWS").with_value("LIGHTS", 8);
+
+        // the same lookups ProgramVariants does
+        let mut variants = HashMap::new();
+        variants.insert(ab.clone(), 1);
+        variants.insert(other_value, 2);
+        assert_eq!(variants.get(&ba), Some(&1));
+        assert_eq!(variants.get(&Defines::new().with("SHADOWS")), None);
+
+        // and the same source, so the program cache finds the variant as well
+        let source = |defines: &Defines| {
+            let preprocessor = Preprocessor::new().with_defines(defines);
+            ShaderSource::from_str("a.vert", "#version 450\nvoid main() {}\n", &preprocessor)
+                .unwrap()
+                .source
+        };
+        assert_eq!(source(&ab), source(&ba));
+    }
 }
@@ -0,0 +1,31 @@
+#version 450 core
+
+in VS_OUTPUT {
+    vec3 Position;
+    vec3 Normal;
+    vec2 Texcoord;
+    vec4 Color;
+} vs_out;
+
+uniform vec4 color;
+uniform vec3 light_position;
+uniform vec3 light_color;
+uniform vec3 view_position;
+uniform float ambient;
+uniform float shininess;
+
+out vec4 Color;
+
+void main()
+{
+    vec3 normal = normalize(vs_out.Normal);
+    vec3 light_dir = normalize(light_position - vs_out.Position);
+    vec3 view_dir = normalize(view_position - vs_out.Position);
+    vec3 halfway = normalize(light_dir + view_dir);
+
+    float diffuse = max(dot(normal, light_dir), 0.0);
+    float specular = pow(max(dot(normal, halfway), 0.0), shininess);
+
+    vec3 lit = (ambient + diffuse) * color.rgb * light_color + specular * light_color;
+    Color = vec4(lit, color.a);
+}
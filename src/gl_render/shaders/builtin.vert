@@ -0,0 +1,24 @@
+#version 450 core
+
+#pragma vertex_inputs(BuiltinVertex)
+
+out VS_OUTPUT {
+    vec3 Position;
+    vec3 Normal;
+    vec2 Texcoord;
+    vec4 Color;
+} vs_out;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+
+void main()
+{
+    vec4 world_position = model * vec4(position, 1.0);
+    gl_Position = projection * view * world_position;
+    vs_out.Position = world_position.xyz;
+    vs_out.Normal = mat3(transpose(inverse(model))) * normal;
+    vs_out.Texcoord = texcoord;
+    vs_out.Color = color;
+}
@@ -0,0 +1,8 @@
+#version 450 core
+
+out vec4 Color;
+
+void main()
+{
+    Color = vec4(1.0, 0.0, 1.0, 1.0);
+}
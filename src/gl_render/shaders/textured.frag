@@ -0,0 +1,17 @@
+#version 450 core
+
+in VS_OUTPUT {
+    vec3 Position;
+    vec3 Normal;
+    vec2 Texcoord;
+    vec4 Color;
+} vs_out;
+
+uniform sampler2D tex;
+
+out vec4 Color;
+
+void main()
+{
+    Color = texture(tex, vs_out.Texcoord);
+}
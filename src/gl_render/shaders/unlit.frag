@@ -0,0 +1,10 @@
+#version 450 core
+
+uniform vec4 color;
+
+out vec4 Color;
+
+void main()
+{
+    Color = color;
+}
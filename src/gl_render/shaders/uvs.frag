@@ -0,0 +1,15 @@
+#version 450 core
+
+in VS_OUTPUT {
+    vec3 Position;
+    vec3 Normal;
+    vec2 Texcoord;
+    vec4 Color;
+} vs_out;
+
+out vec4 Color;
+
+void main()
+{
+    Color = vec4(fract(vs_out.Texcoord), 0.0, 1.0);
+}
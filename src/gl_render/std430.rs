@@ -0,0 +1,199 @@
+use nalgebra_glm as glm;
+
+// a type with a well defined std430 layout that can be placed in a shader storage block
+//
+// for types whose Rust layout differs from std430 the impl must not exist, see
+// #[derive(StorageBlock)] for structs
+pub trait Std430: Copy {
+    const ALIGN: usize;
+    // the std430 size, for arrays of this type the stride is SIZE rounded up to ALIGN
+    const SIZE: usize;
+    // the type GL reports for the member, 0 for structs
+    #[allow(dead_code)]
+    const GL_TYPE: gl::types::GLenum;
+    // 0 if the type is not an array
+    #[allow(dead_code)]
+    const ARRAY_LEN: usize = 0;
+    #[allow(dead_code)]
+    const ARRAY_STRIDE: usize = 0;
+    // the members of a struct or of the elements of an array of structs
+    const MEMBERS: &'static [StorageMember] = &[];
+}
+
+// a struct usable as the whole contents of a shader storage block, see #[derive(StorageBlock)]
+pub trait StorageBlock: Std430 {
+    // element type of the trailing runtime-sized array, () if the block has none
+    type Element: Copy;
+    // where the trailing array starts, size_of::<Self>() if the block has none
+    const ELEMENT_OFFSET: usize;
+}
+
+// describes one member of a storage block, generated by the storage block derive
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StorageMember {
+    pub name: &'static str,
+    pub offset: usize,
+    pub gl_type: gl::types::GLenum,
+    pub array_len: usize,
+    pub array_stride: usize,
+    // the trailing `T name[];` of a block, declared as [T; 0] in Rust
+    pub runtime_sized: bool,
+    pub members: &'static [StorageMember],
+}
+
+pub const fn align_up(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+// used by the code #[derive(StorageBlock)] generates
+#[allow(dead_code)]
+pub const fn max_align(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+macro_rules! std430_type {
+    ($ty:ty, $align:expr, $gl_type:expr) => {
+        impl Std430 for $ty {
+            const ALIGN: usize = $align;
+            const SIZE: usize = std::mem::size_of::<$ty>();
+            const GL_TYPE: gl::types::GLenum = $gl_type;
+        }
+    };
+}
+
+std430_type!(f32, 4, gl::FLOAT);
+std430_type!(i32, 4, gl::INT);
+std430_type!(u32, 4, gl::UNSIGNED_INT);
+std430_type!(f64, 8, gl::DOUBLE);
+std430_type!(glm::Vec2, 8, gl::FLOAT_VEC2);
+std430_type!(glm::Vec3, 16, gl::FLOAT_VEC3);
+std430_type!(glm::Vec4, 16, gl::FLOAT_VEC4);
+std430_type!(glm::IVec2, 8, gl::INT_VEC2);
+std430_type!(glm::IVec3, 16, gl::INT_VEC3);
+std430_type!(glm::IVec4, 16, gl::INT_VEC4);
+std430_type!(glm::UVec2, 8, gl::UNSIGNED_INT_VEC2);
+std430_type!(glm::UVec3, 16, gl::UNSIGNED_INT_VEC3);
+std430_type!(glm::UVec4, 16, gl::UNSIGNED_INT_VEC4);
+std430_type!(glm::Mat2, 8, gl::FLOAT_MAT2);
+std430_type!(glm::Mat4, 16, gl::FLOAT_MAT4);
+// glm::Mat3 is tightly packed while std430 pads every column to a vec4, so it has no impl
+
+impl<T, const N: usize> Std430 for [T; N]
+where
+    T: Std430,
+{
+    const ALIGN: usize = {
+        // Rust arrays are tightly packed, std430 rounds the stride up to the alignment
+        if std::mem::size_of::<T>() != align_up(T::SIZE, T::ALIGN) {
+            panic!("the std430 array stride of the element type differs from its Rust size");
+        }
+        T::ALIGN
+    };
+    const SIZE: usize = std::mem::size_of::<T>() * N;
+    const GL_TYPE: gl::types::GLenum = T::GL_TYPE;
+    const ARRAY_LEN: usize = N;
+    const ARRAY_STRIDE: usize = std::mem::size_of::<T>();
+    const MEMBERS: &'static [StorageMember] = T::MEMBERS;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gl_render_derive::StorageBlock;
+
+    #[derive(Copy, Clone, StorageBlock)]
+    #[repr(C)]
+    struct Light {
+        position: glm::Vec3,
+        intensity: f32,
+        color: glm::Vec4,
+    }
+
+    #[derive(Copy, Clone, StorageBlock)]
+    #[repr(C)]
+    struct PaddedVec3 {
+        value: glm::Vec3,
+        #[padding]
+        _pad: f32,
+    }
+
+    #[derive(Copy, Clone, StorageBlock)]
+    #[repr(C)]
+    struct Scene {
+        count: u32,
+        #[padding]
+        _pad: [u32; 3],
+        lights: [Light; 2],
+        ambient: glm::Vec3,
+        exposure: f32,
+        weights: [f32; 3],
+        #[padding]
+        _pad2: f32,
+        normals: [PaddedVec3; 0],
+    }
+
+    #[test]
+    fn vec3_is_aligned_like_a_vec4_but_has_the_size_of_three_floats() {
+        assert_eq!(<glm::Vec3 as Std430>::ALIGN, 16);
+        assert_eq!(<glm::Vec3 as Std430>::SIZE, 12);
+
+        // a scalar after a vec3 fills its padding
+        let offsets: Vec<_> = Light::MEMBERS.iter().map(|m| m.offset).collect();
+        assert_eq!(offsets, [0, 12, 16]);
+        assert_eq!(Light::ALIGN, 16);
+        assert_eq!(Light::SIZE, 32);
+    }
+
+    #[test]
+    fn arrays_of_scalars_are_tightly_packed() {
+        assert_eq!(<[f32; 3] as Std430>::ALIGN, 4);
+        assert_eq!(<[f32; 3] as Std430>::SIZE, 12);
+        assert_eq!(<[f32; 3] as Std430>::ARRAY_LEN, 3);
+        assert_eq!(<[f32; 3] as Std430>::ARRAY_STRIDE, 4);
+        assert_eq!(<[f32; 3] as Std430>::GL_TYPE, gl::FLOAT);
+    }
+
+    #[test]
+    fn arrays_of_vec3_need_padded_elements() {
+        // [glm::Vec3; N] has no usable impl, std430 strides it by 16 bytes and Rust by 12
+        assert_ne!(
+            std::mem::size_of::<glm::Vec3>(),
+            align_up(<glm::Vec3 as Std430>::SIZE, <glm::Vec3 as Std430>::ALIGN)
+        );
+
+        assert_eq!(<[glm::Vec4; 2] as Std430>::ARRAY_STRIDE, 16);
+        assert_eq!(<[PaddedVec3; 2] as Std430>::ALIGN, 16);
+        assert_eq!(<[PaddedVec3; 2] as Std430>::ARRAY_STRIDE, 16);
+        assert_eq!(<[PaddedVec3; 2] as Std430>::SIZE, 32);
+    }
+
+    #[test]
+    fn nested_structs_keep_their_members_and_stride() {
+        let members: Vec<_> = Scene::MEMBERS
+            .iter()
+            .map(|m| (m.name, m.offset, m.gl_type, m.array_len, m.array_stride))
+            .collect();
+        assert_eq!(
+            members,
+            [
+                ("count", 0, gl::UNSIGNED_INT, 0, 0),
+                ("lights", 16, 0, 2, 32),
+                ("ambient", 80, gl::FLOAT_VEC3, 0, 0),
+                ("exposure", 92, gl::FLOAT, 0, 0),
+                ("weights", 96, gl::FLOAT, 3, 4),
+                ("normals", 112, 0, 0, 16),
+            ]
+        );
+        assert_eq!(Scene::MEMBERS[1].members, Light::MEMBERS);
+        assert_eq!(Scene::MEMBERS[5].members, PaddedVec3::MEMBERS);
+        assert!(Scene::MEMBERS[5].runtime_sized);
+
+        assert_eq!(Scene::ALIGN, 16);
+        assert_eq!(<Scene as StorageBlock>::ELEMENT_OFFSET, 112);
+        assert_eq!(std::mem::size_of::<Scene>(), 112);
+    }
+}
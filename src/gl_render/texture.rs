@@ -7,18 +7,56 @@ use image::imageops as imops;
 use lazy_static::lazy_static;
 
 use crate::resources::{self, Resources};
+use crate::gl_render::uniform::Sampler2D;
 use crate::gl_render::Program;
 
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("failed to load image {name}")]
     ImageLoad { name: String, message: String },
-    #[error("failed to load a resource")]
+    #[error("failed to load a resource: {0}")]
     Resource(#[from] resources::Error),
+    #[error("no texture unit is left for {name}, all {max} are in use")]
+    OutOfTextureUnits { name: String, max: gl::types::GLuint },
+}
+
+// every texture keeps its own unit while it lives, units of dropped textures are handed out again
+struct TextureUnits {
+    next: gl::types::GLuint,
+    free: Vec<gl::types::GLuint>,
 }
 
 lazy_static! {
-    static ref ACTIVE_TEXTURE: Mutex<gl::types::GLuint> = Mutex::new(0);
+    static ref TEXTURE_UNITS: Mutex<TextureUnits> = Mutex::new(TextureUnits {
+        next: 0,
+        free: Vec::new(),
+    });
+}
+
+impl TextureUnits {
+    fn take(gl: &gl::Gl, name: &str) -> Result<gl::types::GLuint, Error> {
+        let mut units = TEXTURE_UNITS.lock().unwrap();
+        if let Some(unit) = units.free.pop() {
+            return Ok(unit);
+        }
+
+        let mut max: gl::types::GLint = 0;
+        unsafe {
+            gl.GetIntegerv(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS, &mut max);
+        }
+        if units.next >= max.max(0) as gl::types::GLuint {
+            return Err(Error::OutOfTextureUnits {
+                name: name.to_string(),
+                max: units.next,
+            });
+        }
+        units.next += 1;
+        Ok(units.next - 1)
+    }
+
+    fn give_back(unit: gl::types::GLuint) {
+        TEXTURE_UNITS.lock().unwrap().free.push(unit);
+    }
 }
 
 pub struct Texture {
@@ -28,8 +66,39 @@ pub struct Texture {
 }
 
 impl Texture {
+    #[allow(dead_code)]
     pub fn new(gl: &gl::Gl, res: &Resources, name: &str, program: &Program, uniform: &str) -> Result<Texture, Error> {
-        let (width, height, img, format) = match res.load_image(name)? {
+        let texture = Texture::from_res(gl, res, name)?;
+        program.get_and_set_1i(uniform, texture.active_id as i32);
+        Ok(texture)
+    }
+
+    // a texture with its own texture unit, not tied to any program
+    pub fn from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<Texture, Error> {
+        Texture::from_image(gl, name, res.load_image(name)?)
+    }
+
+    // magenta and black squares, stands in for textures that failed to load
+    pub fn checkerboard(gl: &gl::Gl) -> Result<Texture, Error> {
+        const SIZE: u32 = 64;
+        const CELL: u32 = 8;
+        let image = image::RgbImage::from_fn(SIZE, SIZE, |x, y| {
+            if (x / CELL + y / CELL) & 1 == 0 {
+                image::Rgb([255, 0, 255])
+            } else {
+                image::Rgb([0, 0, 0])
+            }
+        });
+        Texture::from_image(gl, "<checkerboard>", image::DynamicImage::ImageRgb8(image))
+    }
+
+    // name is only used for errors
+    pub fn from_image(
+        gl: &gl::Gl,
+        name: &str,
+        image: image::DynamicImage,
+    ) -> Result<Texture, Error> {
+        let (width, height, img, format) = match image {
             image::DynamicImage::ImageRgb8(mut img) => {
                 imops::flip_vertical_in_place(&mut img);
                 (img.width(), img.height(), img.into_vec(), gl::RGB)
@@ -46,72 +115,65 @@ impl Texture {
             }
         };
 
+        let internal_format = match format {
+            gl::RGBA => gl::RGBA8,
+            _ => gl::RGB8,
+        };
+        let levels = 32 - width.max(height).leading_zeros();
+
+        let active_id = TextureUnits::take(gl, name)?;
+
         let mut id: gl::types::GLuint = 0;
         unsafe {
-            gl.GenTextures(1, &mut id);
+            gl.CreateTextures(gl::TEXTURE_2D, 1, &mut id);
         }
 
-        let mut active_tex_lock = ACTIVE_TEXTURE.lock().unwrap();
-        let active_id = *active_tex_lock;
-        *active_tex_lock += 1;
-
         unsafe {
-            // bind
-            gl.ActiveTexture(gl::TEXTURE0 + active_id);
-            program.get_and_set_1i(uniform, active_id as i32);
-            gl.BindTexture(gl::TEXTURE_2D, id);
             // set wrapping
-            gl.TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_S,
-                gl::REPEAT as gl::types::GLint,
-            );
-            gl.TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_T,
-                gl::REPEAT as gl::types::GLint,
-            );
+            gl.TextureParameteri(id, gl::TEXTURE_WRAP_S, gl::REPEAT as gl::types::GLint);
+            gl.TextureParameteri(id, gl::TEXTURE_WRAP_T, gl::REPEAT as gl::types::GLint);
             // set filtering
-            gl.TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_MIN_FILTER,
-                gl::LINEAR as gl::types::GLint,
-            );
-            gl.TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_MAG_FILTER,
-                gl::LINEAR as gl::types::GLint,
+            gl.TextureParameteri(id, gl::TEXTURE_MIN_FILTER, gl::LINEAR as gl::types::GLint);
+            gl.TextureParameteri(id, gl::TEXTURE_MAG_FILTER, gl::LINEAR as gl::types::GLint);
+            // allocate immutable storage and buffer image
+            gl.TextureStorage2D(
+                id,
+                levels as gl::types::GLsizei,
+                internal_format,
+                width as gl::types::GLsizei,
+                height as gl::types::GLsizei,
             );
-            // buffer image
-            gl.TexImage2D(
-                gl::TEXTURE_2D,
+            gl.TextureSubImage2D(
+                id,
+                0,
+                0,
                 0,
-                gl::RGB as gl::types::GLint,
                 width as gl::types::GLsizei,
                 height as gl::types::GLsizei,
-                0,
                 format,
                 gl::UNSIGNED_BYTE,
                 img.as_ptr() as *const gl::types::GLvoid,
             );
-            gl.GenerateMipmap(gl::TEXTURE_2D);
-            // unbind
-            gl.BindTexture(gl::TEXTURE_2D, 0);
+            gl.GenerateTextureMipmap(id);
         }
 
         Ok(Texture { active_id, id, gl: gl.clone() })
     }
 
+    // the texture unit this texture binds to, for use as a sampler uniform
+    pub fn sampler(&self) -> Sampler2D {
+        Sampler2D(self.active_id)
+    }
+
     pub fn bind(&self) {
         unsafe {
-            self.gl.ActiveTexture(gl::TEXTURE0 + self.active_id);
-            self.gl.BindTexture(gl::TEXTURE_2D, self.id);
+            self.gl.BindTextureUnit(self.active_id, self.id);
         }
     }
 
     pub fn unbind(&self) {
         unsafe {
-            self.gl.BindTexture(gl::TEXTURE_2D, 0);
+            self.gl.BindTextureUnit(self.active_id, 0);
         }
     }
 }
@@ -121,5 +183,6 @@ impl Drop for Texture {
         unsafe {
             self.gl.DeleteTextures(1, &self.id);
         }
+        TextureUnits::give_back(self.active_id);
     }
 }
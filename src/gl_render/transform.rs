@@ -2,11 +2,13 @@ use nalgebra_glm as glm;
 
 use super::Program;
 
+#[allow(dead_code)]
 pub struct Transform<'a> {
     program: &'a Program,
     loc: gl::types::GLint,
 }
 
+#[allow(dead_code)]
 impl<'a> Transform<'a> {
     pub fn new(program: &'a Program, field: &str) -> Transform<'a> {
         Transform {
@@ -0,0 +1,262 @@
+use std::ops::{Deref, DerefMut};
+
+use nalgebra_glm as glm;
+
+use super::shader::{Error, Program, UniformProblem};
+
+// a value that can be uploaded to a default block uniform
+pub trait Uniform {
+    const GL_TYPE: gl::types::GLenum;
+
+    fn set(program: &Program, location: gl::types::GLint, value: &Self);
+}
+
+// a texture unit read through a sampler2D uniform
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sampler2D(pub gl::types::GLuint);
+
+impl Uniform for Sampler2D {
+    const GL_TYPE: gl::types::GLenum = gl::SAMPLER_2D;
+
+    fn set(program: &Program, location: gl::types::GLint, value: &Self) {
+        program.set_1i(location, value.0 as gl::types::GLint);
+    }
+}
+
+impl Uniform for f32 {
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT;
+
+    fn set(program: &Program, location: gl::types::GLint, value: &Self) {
+        program.set_1f(location, *value);
+    }
+}
+
+impl Uniform for i32 {
+    const GL_TYPE: gl::types::GLenum = gl::INT;
+
+    fn set(program: &Program, location: gl::types::GLint, value: &Self) {
+        program.set_1i(location, *value);
+    }
+}
+
+impl Uniform for u32 {
+    const GL_TYPE: gl::types::GLenum = gl::UNSIGNED_INT;
+
+    fn set(program: &Program, location: gl::types::GLint, value: &Self) {
+        program.set_1ui(location, *value);
+    }
+}
+
+impl Uniform for bool {
+    const GL_TYPE: gl::types::GLenum = gl::BOOL;
+
+    fn set(program: &Program, location: gl::types::GLint, value: &Self) {
+        program.set_1i(location, *value as gl::types::GLint);
+    }
+}
+
+impl Uniform for glm::Vec2 {
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT_VEC2;
+
+    fn set(program: &Program, location: gl::types::GLint, value: &Self) {
+        program.set_2fv(location, value);
+    }
+}
+
+impl Uniform for glm::Vec3 {
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT_VEC3;
+
+    fn set(program: &Program, location: gl::types::GLint, value: &Self) {
+        program.set_3fv(location, value);
+    }
+}
+
+impl Uniform for glm::Vec4 {
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT_VEC4;
+
+    fn set(program: &Program, location: gl::types::GLint, value: &Self) {
+        program.set_4fv(location, value);
+    }
+}
+
+impl Uniform for glm::Mat3 {
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT_MAT3;
+
+    fn set(program: &Program, location: gl::types::GLint, value: &Self) {
+        program.set_matrix3fv(location, value);
+    }
+}
+
+impl Uniform for glm::Mat4 {
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT_MAT4;
+
+    fn set(program: &Program, location: gl::types::GLint, value: &Self) {
+        program.set_matrix4fv(location, value);
+    }
+}
+
+// describes one field of a uniforms struct, generated by the uniforms derive
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UniformDesc {
+    pub name: &'static str,
+    pub gl_type: gl::types::GLenum,
+}
+
+// a struct whose fields map one to one to uniforms of a program, see #[derive(ProgramUniforms)]
+pub trait ProgramUniforms {
+    const UNIFORMS: &'static [UniformDesc];
+
+    // locations are given in the order of UNIFORMS
+    fn upload(&self, program: &Program, locations: &[gl::types::GLint]);
+}
+
+// uniform values together with their locations in one program
+pub struct Uniforms<U> {
+    values: U,
+    program: gl::types::GLuint,
+    locations: Vec<gl::types::GLint>,
+}
+
+impl<U> Uniforms<U>
+where
+    U: ProgramUniforms,
+{
+    // resolves every uniform of U in program, failing if any is missing or has another type
+    pub fn new(program: &Program, values: U) -> Result<Uniforms<U>, Error> {
+        Ok(Uniforms {
+            values,
+            program: program.id(),
+            locations: Self::locations(program)?,
+        })
+    }
+
+    // like new, but uniforms that are missing or have another type are skipped, for programs
+    // that stand in for the one U was written for
+    pub fn new_partial(program: &Program, values: U) -> Uniforms<U> {
+        Uniforms {
+            values,
+            program: program.id(),
+            locations: Self::partial_locations(program),
+        }
+    }
+
+    // the id of the program the locations belong to
+    pub fn program(&self) -> gl::types::GLuint {
+        self.program
+    }
+
+    // resolves the locations again, keeping the values, for when the program was replaced,
+    // e.g. by a reload. On an error the uniforms are left as they were
+    pub fn rebind(&mut self, program: &Program) -> Result<(), Error> {
+        self.locations = Self::locations(program)?;
+        self.program = program.id();
+        Ok(())
+    }
+
+    // like rebind, skipping what is missing like new_partial
+    pub fn rebind_partial(&mut self, program: &Program) {
+        self.locations = Self::partial_locations(program);
+        self.program = program.id();
+    }
+
+    fn locations(program: &Program) -> Result<Vec<gl::types::GLint>, Error> {
+        let active = program.active_uniforms();
+        let mut locations = Vec::with_capacity(U::UNIFORMS.len());
+        let mut problems = Vec::new();
+
+        for desc in U::UNIFORMS {
+            match active.iter().find(|uniform| uniform.name == desc.name) {
+                None => problems.push(UniformProblem::Missing {
+                    name: desc.name,
+                    glsl_type: uniform_type_name(desc.gl_type),
+                }),
+                Some(uniform) if uniform.gl_type != desc.gl_type => {
+                    problems.push(UniformProblem::Mistyped {
+                        name: desc.name,
+                        expected: uniform_type_name(uniform.gl_type),
+                        found: uniform_type_name(desc.gl_type),
+                    })
+                }
+                Some(uniform) => locations.push(uniform.location),
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(Error::UniformMismatch { problems });
+        }
+
+        Ok(locations)
+    }
+
+    fn partial_locations(program: &Program) -> Vec<gl::types::GLint> {
+        let active = program.active_uniforms();
+        U::UNIFORMS
+            .iter()
+            .map(|desc| {
+                match active
+                    .iter()
+                    .find(|uniform| uniform.name == desc.name && uniform.gl_type == desc.gl_type)
+                {
+                    Some(uniform) => uniform.location,
+                    // setting location -1 is ignored by GL
+                    None => -1,
+                }
+            })
+            .collect()
+    }
+
+    pub fn apply(&self, program: &Program) {
+        debug_assert_eq!(
+            program.id(),
+            self.program,
+            "uniforms were resolved against another program"
+        );
+        self.values.upload(program, &self.locations);
+    }
+}
+
+impl<U> Deref for Uniforms<U> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        &self.values
+    }
+}
+
+impl<U> DerefMut for Uniforms<U> {
+    fn deref_mut(&mut self) -> &mut U {
+        &mut self.values
+    }
+}
+
+pub fn uniform_type_name(gl_type: gl::types::GLenum) -> &'static str {
+    match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::DOUBLE => "double",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        _ => "<unknown>",
+    }
+}
@@ -1,4 +1,6 @@
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 use glutin::dpi;
 use glutin::event::{Event, WindowEvent};
@@ -11,11 +13,12 @@ use glutin::GlRequest;
 
 use nalgebra_glm as glm;
 
+mod assets;
+use assets::AssetServer;
 mod gl_render;
 use gl_render::buffer;
 use gl_render::color_buffer::ColorBuffer;
-use gl_render::Transform;
-use gl_render::Viewport;
+use gl_render::{Preprocessor, ProgramCache, Viewport};
 mod resources;
 use resources::Resources;
 mod square;
@@ -25,7 +28,10 @@ const HEIGHT: i32 = 600;
 
 fn main() {
     // create resource loader
-    let res = Resources::from_relative_exe_path(Path::new("assets")).unwrap();
+    #[cfg(feature = "embed_assets")]
+    let res = Resources::embedded();
+    #[cfg(not(feature = "embed_assets"))]
+    let res = Resources::locate(Path::new("assets.pack")).unwrap();
     // create event loop
     let el = EventLoop::new();
     // create window builder
@@ -50,27 +56,34 @@ fn main() {
     let color_buffer = ColorBuffer::from_color(glm::Vec3::new(0.3, 0.3, 0.5));
     color_buffer.set_used(&gl);
 
-    let square = square::Square::new(&res, &gl).unwrap();
+    // program binaries are only cached when a directory for them is given
+    let program_cache =
+        std::env::var_os("PROGRAM_CACHE_DIR").map(|dir| ProgramCache::new(&gl, Path::new(&dir)));
+
+    let assets = AssetServer::new(&gl, Arc::new(res))
+        .with_preprocessor(
+            Preprocessor::new().with_vertex_inputs("Vertex", square::Vertex::GLSL_INPUTS),
+        )
+        .with_program_cache(program_cache)
+        .with_fallbacks(cfg!(debug_assertions) || std::env::var_os("ASSET_FALLBACKS").is_some());
+
+    let mut square = square::Square::new(&assets, &gl).unwrap();
+    if let Some(program) = square.program().get() {
+        for warning in program.warnings() {
+            eprintln!("{}", warning);
+        }
+    }
 
     // create transforms
-    square.program().set_used();
-    let model_matrix = glm::Mat4::identity();
-    let model_matrix = glm::rotate(
-        &model_matrix,
+    let uniforms = square.uniforms_mut();
+    uniforms.model = glm::rotate(
+        &glm::Mat4::identity(),
         -55f32.to_radians(),
         &glm::vec3(1.0, 0.0, 0.0),
     );
-    let model = Transform::new(&square.program(), "model");
-    model.set_matrix(&model_matrix);
-    let view_matrix = glm::Mat4::identity();
-    let view_matrix = glm::translate(&view_matrix, &glm::vec3(0.0, 0.0, -3.0));
-    let view = Transform::new(&square.program(), "view");
-    view.set_matrix(&view_matrix);
-    let proj_matrix =
+    uniforms.view = glm::translate(&glm::Mat4::identity(), &glm::vec3(0.0, 0.0, -3.0));
+    uniforms.projection =
         glm::perspective(45f32.to_radians(), WIDTH as f32 / HEIGHT as f32, 0.1, 100.0);
-    let proj = Transform::new(&square.program(), "projection");
-    proj.set_matrix(&proj_matrix);
-    square.program().set_unused();
 
     el.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
@@ -90,6 +103,17 @@ fn main() {
             _ => (),
         }
 
+        // leave most of the frame for drawing
+        if assets.update(Duration::from_millis(4)) > 0 {
+            let progress = assets.progress();
+            let title = if progress.is_done() {
+                "Hello world!".to_string()
+            } else {
+                format!("Hello world! loading {:.0}%", progress.fraction() * 100.0)
+            };
+            gl_window.window().set_title(&title);
+        }
+
         color_buffer.clear(&gl);
         square.render(&gl);
 
@@ -1,73 +0,0 @@
-use std::ffi;
-use std::fs;
-use std::io::{self, Read};
-use std::path::{Path, PathBuf};
-
-use thiserror::Error;
-
-use image::DynamicImage;
-
-#[derive(Error, Debug)]
-pub enum Error {
-    #[error("failed to get executable path")]
-    FailedToGetExePath,
-    #[error("I/O error")]
-    Io(io::Error),
-    #[error("Image load error")]
-    Image(image::ImageError),
-    #[error("failed to read CString from file that contains 0")]
-    FileContainsNil,
-}
-
-impl From<io::Error> for Error {
-    fn from(other: io::Error) -> Self {
-        Error::Io(other)
-    }
-}
-
-impl From<image::ImageError> for Error {
-    fn from(other: image::ImageError) -> Self {
-        Error::Image(other)
-    }
-}
-
-pub struct Resources {
-    root_path: PathBuf,
-}
-
-impl Resources {
-    pub fn from_relative_exe_path(rel_path: &Path) -> Result<Resources, Error> {
-        let exe_file_name = std::env::current_exe().map_err(|_| Error::FailedToGetExePath)?;
-        let exe_path = exe_file_name.parent().ok_or(Error::FailedToGetExePath)?;
-        Ok(Resources {
-            root_path: exe_path.join(rel_path),
-        })
-    }
-
-    pub fn load_cstring(&self, resource_name: &str) -> Result<ffi::CString, Error> {
-        let resource_name = resource_name_to_path(&self.root_path, resource_name);
-        let mut file = fs::File::open(self.root_path.join(resource_name))?;
-        let mut buffer: Vec<u8> = Vec::with_capacity(file.metadata()?.len() as usize + 1);
-        file.read_to_end(&mut buffer)?;
-        if buffer.iter().find(|i| **i == 0).is_some() {
-            return Err(Error::FileContainsNil);
-        }
-
-        Ok(unsafe { ffi::CString::from_vec_unchecked(buffer) })
-    }
-
-    pub fn load_image(&self, resource_name: &str) -> Result<DynamicImage, Error> {
-        let resource_name = resource_name_to_path(&self.root_path, resource_name);
-        Ok(image::open(self.root_path.join(resource_name))?)
-    }
-}
-
-fn resource_name_to_path(root_dir: &Path, location: &str) -> PathBuf {
-    let mut path: PathBuf = root_dir.into();
-
-    for part in location.split("/") {
-        path = path.join(part);
-    }
-
-    path
-}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use zip::ZipArchive;
+
+use super::{Error, ResourceSource};
+
+// resources packed into a single zip or uncompressed tar file
+pub struct ArchiveSource {
+    path: PathBuf,
+    archive: Archive,
+}
+
+// resource name to the offset and size of its data in the file
+type TarEntries = HashMap<String, (u64, u64)>;
+
+enum Archive {
+    Zip(Mutex<ZipArchive<File>>),
+    Tar {
+        file: Mutex<File>,
+        entries: TarEntries,
+    },
+}
+
+#[allow(dead_code)]
+impl ArchiveSource {
+    // the format is taken from the extension, .zip or .tar
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<ArchiveSource, Error> {
+        let path = path.into();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("zip") => ArchiveSource::zip(path),
+            Some("tar") => ArchiveSource::tar(path),
+            _ => Err(Error::OpenArchive {
+                path,
+                inner: io::Error::new(io::ErrorKind::InvalidInput, "not a .zip or .tar archive"),
+            }),
+        }
+    }
+
+    pub fn zip<P: Into<PathBuf>>(path: P) -> Result<ArchiveSource, Error> {
+        let path = path.into();
+        let archive = File::open(&path)
+            .and_then(|file| ZipArchive::new(file).map_err(io::Error::from))
+            .map_err(|e| Error::OpenArchive {
+                path: path.clone(),
+                inner: e,
+            })?;
+        Ok(ArchiveSource {
+            path,
+            archive: Archive::Zip(Mutex::new(archive)),
+        })
+    }
+
+    // tar has no index, so the entries are located once when opening
+    pub fn tar<P: Into<PathBuf>>(path: P) -> Result<ArchiveSource, Error> {
+        let path = path.into();
+        let (file, entries) = index_tar(&path).map_err(|e| Error::OpenArchive {
+            path: path.clone(),
+            inner: e,
+        })?;
+        Ok(ArchiveSource {
+            path,
+            archive: Archive::Tar {
+                file: Mutex::new(file),
+                entries,
+            },
+        })
+    }
+
+    pub fn archive_path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl ResourceSource for ArchiveSource {
+    fn read(&self, name: &str) -> Result<Vec<u8>, Error> {
+        let result = match &self.archive {
+            Archive::Zip(archive) => {
+                let mut archive = archive.lock().unwrap();
+                let result = match archive.by_name(name) {
+                    // the size in the header is not trusted for allocating, a damaged archive
+                    // can claim anything
+                    Ok(file) => {
+                        let size = file.size();
+                        let mut buffer = Vec::new();
+                        file.take(size)
+                            .read_to_end(&mut buffer)
+                            .map(|_| Some(buffer))
+                    }
+                    Err(zip::result::ZipError::FileNotFound) => Ok(None),
+                    Err(err) => Err(err.into()),
+                };
+                result
+            }
+            Archive::Tar { file, entries } => match entries.get(name) {
+                Some(&(offset, size)) => {
+                    let mut file = file.lock().unwrap();
+                    read_tar_entry(&mut file, offset, size).map(Some)
+                }
+                None => Ok(None),
+            },
+        };
+
+        match result {
+            Ok(Some(buffer)) => Ok(buffer),
+            Ok(None) => Err(Error::NotFound {
+                name: name.into(),
+                path: ResourceSource::path(self, name),
+            }),
+            Err(err) => Err(Error::Io {
+                name: name.into(),
+                path: ResourceSource::path(self, name),
+                inner: err,
+            }),
+        }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        match &self.archive {
+            Archive::Zip(archive) => archive.lock().unwrap().by_name(name).is_ok(),
+            Archive::Tar { entries, .. } => entries.contains_key(name),
+        }
+    }
+
+    // entries are shown as if the archive was a directory
+    fn path(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+// reads up to size bytes instead of allocating them up front, the header of a truncated
+// archive still claims the full size
+fn read_tar_entry(file: &mut File, offset: u64, size: u64) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = Vec::new();
+    file.take(size).read_to_end(&mut buffer)?;
+    if (buffer.len() as u64) < size {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "the archive ends inside the entry",
+        ));
+    }
+    Ok(buffer)
+}
+
+fn index_tar(path: &Path) -> io::Result<(File, TarEntries)> {
+    let mut entries = HashMap::new();
+    let mut archive = tar::Archive::new(File::open(path)?);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = tar_entry_name(&entry.path()?);
+        entries.insert(name, (entry.raw_file_position(), entry.size()));
+    }
+    Ok((File::open(path)?, entries))
+}
+
+// `tar -cf assets.tar .` stores every path below ./
+fn tar_entry_name(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            std::path::Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::process;
+
+    #[test]
+    fn tar_entries_past_the_end_are_an_error() {
+        let path =
+            std::env::temp_dir().join(format!("opengl-globe-tar-entry-{}.bin", process::id()));
+        let data: Vec<u8> = (0..100).collect();
+        File::create(&path).unwrap().write_all(&data).unwrap();
+        let mut file = File::open(&path).unwrap();
+
+        assert_eq!(read_tar_entry(&mut file, 10, 50).unwrap(), &data[10..60]);
+        let err = read_tar_entry(&mut file, 10, u64::MAX).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,74 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::{validate_name, Error, ReadSeek, ResourceSource};
+
+// loose files below a root directory
+pub struct DirectorySource {
+    root_path: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new<P: Into<PathBuf>>(root_path: P) -> DirectorySource {
+        DirectorySource {
+            root_path: root_path.into(),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+}
+
+impl ResourceSource for DirectorySource {
+    fn read(&self, name: &str) -> Result<Vec<u8>, Error> {
+        // this is the one source that can reach outside of its root, so check again in case it
+        // is used without Resources
+        validate_name(name)?;
+        let path = self.path(name);
+        fs::read(&path).map_err(|err| io_error(name, path, err))
+    }
+
+    fn open(&self, name: &str) -> Result<Box<dyn ReadSeek>, Error> {
+        validate_name(name)?;
+        let path = self.path(name);
+        match fs::File::open(&path) {
+            Ok(file) => Ok(Box::new(io::BufReader::new(file))),
+            Err(err) => Err(io_error(name, path, err)),
+        }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        validate_name(name).is_ok() && self.path(name).is_file()
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        resource_name_to_path(&self.root_path, name)
+    }
+}
+
+fn io_error(name: &str, path: PathBuf, err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::NotFound => Error::NotFound {
+            name: name.into(),
+            path,
+        },
+        _ => Error::Io {
+            name: name.into(),
+            path,
+            inner: err,
+        },
+    }
+}
+
+fn resource_name_to_path(root_dir: &Path, location: &str) -> PathBuf {
+    let mut path: PathBuf = root_dir.into();
+
+    for part in location.split('/') {
+        path = path.join(part);
+    }
+
+    path
+}
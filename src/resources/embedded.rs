@@ -0,0 +1,60 @@
+use std::io;
+use std::path::PathBuf;
+
+use super::{Error, ReadSeek, ResourceSource};
+
+mod assets {
+    include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+}
+
+// the assets/ tree compiled into the executable by build.rs, see the embed_assets feature
+#[derive(Default)]
+pub struct EmbeddedSource;
+
+impl EmbeddedSource {
+    pub fn new() -> EmbeddedSource {
+        EmbeddedSource
+    }
+
+    fn get(&self, name: &str) -> Option<&'static [u8]> {
+        // build.rs sorts the table by name
+        assets::ASSETS
+            .binary_search_by_key(&name, |&(asset, _)| asset)
+            .ok()
+            .map(|index| assets::ASSETS[index].1)
+    }
+
+    #[allow(dead_code)]
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        assets::ASSETS.iter().map(|&(name, _)| name)
+    }
+}
+
+impl ResourceSource for EmbeddedSource {
+    fn read(&self, name: &str) -> Result<Vec<u8>, Error> {
+        self.get(name)
+            .map(|contents| contents.to_vec())
+            .ok_or_else(|| Error::NotFound {
+                name: name.into(),
+                path: self.path(name),
+            })
+    }
+
+    fn open(&self, name: &str) -> Result<Box<dyn ReadSeek>, Error> {
+        match self.get(name) {
+            Some(contents) => Ok(Box::new(io::Cursor::new(contents))),
+            None => Err(Error::NotFound {
+                name: name.into(),
+                path: self.path(name),
+            }),
+        }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        PathBuf::from("<embedded>").join(name)
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::{Error, ResourceSource};
+
+// resources held in memory, mostly for tests and generated content
+#[derive(Default)]
+pub struct MemorySource {
+    resources: HashMap<String, Vec<u8>>,
+}
+
+#[allow(dead_code)]
+impl MemorySource {
+    pub fn new() -> MemorySource {
+        MemorySource::default()
+    }
+
+    pub fn with<B: Into<Vec<u8>>>(mut self, name: &str, contents: B) -> MemorySource {
+        self.insert(name, contents);
+        self
+    }
+
+    // replaces the resource if there already is one with the name
+    pub fn insert<B: Into<Vec<u8>>>(&mut self, name: &str, contents: B) {
+        self.resources.insert(name.into(), contents.into());
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Vec<u8>> {
+        self.resources.remove(name)
+    }
+}
+
+impl ResourceSource for MemorySource {
+    fn read(&self, name: &str) -> Result<Vec<u8>, Error> {
+        self.resources
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::NotFound {
+                name: name.into(),
+                path: self.path(name),
+            })
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.resources.contains_key(name)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        PathBuf::from("<memory>").join(name)
+    }
+}
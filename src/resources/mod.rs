@@ -0,0 +1,311 @@
+use std::ffi;
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use image::DynamicImage;
+use serde::de::DeserializeOwned;
+
+mod archive;
+mod directory;
+#[cfg(feature = "embed_assets")]
+mod embedded;
+mod memory;
+mod overlay;
+mod pack;
+mod pack_format;
+mod search;
+
+#[allow(unused_imports)]
+pub use self::archive::ArchiveSource;
+pub use self::directory::DirectorySource;
+#[cfg(feature = "embed_assets")]
+pub use self::embedded::EmbeddedSource;
+#[allow(unused_imports)]
+pub use self::memory::MemorySource;
+#[allow(unused_imports)]
+pub use self::overlay::OverlaySource;
+#[allow(unused_imports)]
+pub use self::pack::PackSource;
+#[allow(unused_imports)]
+pub use self::search::{SearchPaths, ASSETS_DIR_VAR};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to get executable path")]
+    FailedToGetExePath,
+    #[error("no resource directory or archive found, tried {}", search::display_paths(.tried))]
+    NoResourceDir { tried: Vec<PathBuf> },
+    #[error("failed to open resource archive {}: {inner}", .path.display())]
+    OpenArchive { path: PathBuf, inner: io::Error },
+    #[error("invalid resource name {name:?}: {reason}")]
+    InvalidName { name: String, reason: &'static str },
+    #[error("resource {name} not found at {}", .path.display())]
+    NotFound { name: String, path: PathBuf },
+    #[error("failed to read resource {name} from {}: {inner}", .path.display())]
+    Io {
+        name: String,
+        path: PathBuf,
+        inner: io::Error,
+    },
+    #[error("resource {name} at {} does not match the pack manifest", .path.display())]
+    Corrupt { name: String, path: PathBuf },
+    #[error("failed to decode image {name} from {}: {inner}", .path.display())]
+    Image {
+        name: String,
+        path: PathBuf,
+        // boxed, it would make every Result of this module several times larger
+        inner: Box<image::ImageError>,
+    },
+    #[error("resource {name} at {} contains a nul byte", .path.display())]
+    FileContainsNil { name: String, path: PathBuf },
+    #[error("resource {name} at {} is not valid UTF-8: {inner}", .path.display())]
+    InvalidUtf8 {
+        name: String,
+        path: PathBuf,
+        inner: std::str::Utf8Error,
+    },
+    #[error("failed to parse {format} resource {name} from {}: {inner}", .path.display())]
+    Deserialize {
+        name: String,
+        path: PathBuf,
+        format: &'static str,
+        inner: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+// resource names are relative paths with / as the separator, anything that could point outside
+// of the root of a source is rejected
+pub fn validate_name(name: &str) -> Result<(), Error> {
+    let reason = if name.is_empty() {
+        Some("the name is empty")
+    } else if name.contains('\\') {
+        Some("backslashes are not allowed, use / as the separator")
+    } else if name.starts_with('/') {
+        Some("the name must be relative")
+    } else if name.contains('\0') {
+        Some("the name contains a nul byte")
+    } else {
+        name.split('/').find_map(|part| match part {
+            "" => Some("the name contains an empty component"),
+            "." | ".." => Some("the name contains a . or .. component"),
+            // drive prefixes like C: would make the joined path absolute on Windows
+            _ if part.contains(':') => Some("the name contains a :"),
+            _ => None,
+        })
+    };
+
+    match reason {
+        Some(reason) => Err(Error::InvalidName {
+            name: name.into(),
+            reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+// somewhere resources can be read from, resource names always use / as the separator
+//
+// names passed to a source are validated with validate_name first
+pub trait ResourceSource: Send + Sync {
+    // Error::NotFound if the source does not have the resource
+    fn read(&self, name: &str) -> Result<Vec<u8>, Error>;
+
+    fn contains(&self, name: &str) -> bool;
+
+    // where the resource is or would be read from, for error messages
+    fn path(&self, name: &str) -> PathBuf;
+
+    // sources that can stream should, the default reads the whole resource up front
+    fn open(&self, name: &str) -> Result<Box<dyn ReadSeek>, Error> {
+        Ok(Box::new(io::Cursor::new(self.read(name)?)))
+    }
+}
+
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+pub struct Resources {
+    source: Box<dyn ResourceSource>,
+}
+
+impl Resources {
+    pub fn new<S: ResourceSource + 'static>(source: S) -> Resources {
+        Resources {
+            source: Box::new(source),
+        }
+    }
+
+    // the first existing directory or archive of SearchPaths::standard
+    #[allow(dead_code)]
+    pub fn locate(rel_path: &Path) -> Result<Resources, Error> {
+        Resources::from_search_paths(&SearchPaths::standard(rel_path))
+    }
+
+    #[allow(dead_code)]
+    pub fn from_search_paths(paths: &SearchPaths) -> Result<Resources, Error> {
+        Resources::mount(paths.find()?)
+    }
+
+    // a directory, a .pack file or a .zip or .tar archive
+    pub fn mount(path: &Path) -> Result<Resources, Error> {
+        if path.is_dir() {
+            return Ok(Resources::new(DirectorySource::new(path)));
+        }
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("pack") => Ok(Resources::new(PackSource::open(path)?)),
+            _ => Ok(Resources::new(ArchiveSource::open(path)?)),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn from_relative_exe_path(rel_path: &Path) -> Result<Resources, Error> {
+        let exe_file_name = std::env::current_exe().map_err(|_| Error::FailedToGetExePath)?;
+        let exe_path = exe_file_name.parent().ok_or(Error::FailedToGetExePath)?;
+        Ok(Resources::new(DirectorySource::new(
+            exe_path.join(rel_path),
+        )))
+    }
+
+    // the assets/ tree built into the executable
+    #[cfg(feature = "embed_assets")]
+    pub fn embedded() -> Resources {
+        Resources::new(EmbeddedSource::new())
+    }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, resource_name: &str) -> bool {
+        validate_name(resource_name).is_ok() && self.source.contains(resource_name)
+    }
+
+    pub fn load_bytes(&self, resource_name: &str) -> Result<Vec<u8>, Error> {
+        validate_name(resource_name)?;
+        self.source.read(resource_name)
+    }
+
+    #[allow(dead_code)]
+    pub fn load_string(&self, resource_name: &str) -> Result<String, Error> {
+        let buffer = self.load_bytes(resource_name)?;
+        String::from_utf8(buffer).map_err(|e| Error::InvalidUtf8 {
+            name: resource_name.into(),
+            path: self.source.path(resource_name),
+            inner: e.utf8_error(),
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn open(&self, resource_name: &str) -> Result<impl Read + Seek, Error> {
+        validate_name(resource_name)?;
+        self.source.open(resource_name)
+    }
+
+    pub fn load_cstring(&self, resource_name: &str) -> Result<ffi::CString, Error> {
+        let buffer = self.load_bytes(resource_name)?;
+        ffi::CString::new(buffer).map_err(|_| Error::FileContainsNil {
+            name: resource_name.into(),
+            path: self.source.path(resource_name),
+        })
+    }
+
+    pub fn load_image(&self, resource_name: &str) -> Result<DynamicImage, Error> {
+        let buffer = self.load_bytes(resource_name)?;
+        // the contents decide if the extension is unknown
+        match image::ImageFormat::from_path(resource_name) {
+            Ok(format) => image::load_from_memory_with_format(&buffer, format),
+            Err(_) => image::load_from_memory(&buffer),
+        }
+        .map_err(|e| Error::Image {
+            name: resource_name.into(),
+            path: self.source.path(resource_name),
+            inner: Box::new(e),
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn load_ron<T: DeserializeOwned>(&self, resource_name: &str) -> Result<T, Error> {
+        let buffer = self.load_bytes(resource_name)?;
+        ron::de::from_bytes(&buffer)
+            .map_err(|e| self.deserialize_error(resource_name, "RON", Box::new(e)))
+    }
+
+    #[allow(dead_code)]
+    pub fn load_json<T: DeserializeOwned>(&self, resource_name: &str) -> Result<T, Error> {
+        let buffer = self.load_bytes(resource_name)?;
+        serde_json::from_slice(&buffer)
+            .map_err(|e| self.deserialize_error(resource_name, "JSON", Box::new(e)))
+    }
+
+    #[allow(dead_code)]
+    pub fn load_toml<T: DeserializeOwned>(&self, resource_name: &str) -> Result<T, Error> {
+        let string = self.load_string(resource_name)?;
+        toml::from_str(&string)
+            .map_err(|e| self.deserialize_error(resource_name, "TOML", Box::new(e)))
+    }
+
+    fn deserialize_error(
+        &self,
+        resource_name: &str,
+        format: &'static str,
+        inner: Box<dyn std::error::Error + Send + Sync>,
+    ) -> Error {
+        Error::Deserialize {
+            name: resource_name.into(),
+            path: self.source.path(resource_name),
+            format,
+            inner,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_names() {
+        for name in &[
+            "shaders/globe.vert",
+            "textures/earth.png",
+            "a",
+            ".hidden",
+            "models/..cube.obj",
+        ] {
+            assert!(validate_name(name).is_ok(), "{} was rejected", name);
+        }
+    }
+
+    #[test]
+    fn invalid_names() {
+        // each name with a part of the reason it has to be rejected for
+        let cases = [
+            ("", "is empty"),
+            ("..", ". or .."),
+            ("../secret.txt", ". or .."),
+            ("shaders/../../secret.txt", ". or .."),
+            ("shaders/./globe.vert", ". or .."),
+            ("/etc/passwd", "relative"),
+            ("shaders\\globe.vert", "backslashes"),
+            ("\\\\server\\share", "backslashes"),
+            ("C:/Windows/win.ini", ":"),
+            ("C:secret.txt", ":"),
+            ("shaders/", "empty component"),
+            ("shaders//globe.vert", "empty component"),
+            ("globe.vert\0", "nul byte"),
+        ];
+
+        for &(name, expected) in &cases {
+            match validate_name(name) {
+                Err(Error::InvalidName {
+                    name: rejected,
+                    reason,
+                }) => {
+                    assert_eq!(rejected, name);
+                    assert!(reason.contains(expected), "{:?}: {}", name, reason);
+                }
+                other => panic!("unexpected {:?} for {:?}", other, name),
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use super::{Error, ReadSeek, ResourceSource};
+
+// layers several sources, a resource is read from the topmost layer that has it so mods and
+// patches can replace single files of the base assets
+#[derive(Default)]
+pub struct OverlaySource {
+    // the last layer is the topmost
+    layers: Vec<Box<dyn ResourceSource>>,
+}
+
+#[allow(dead_code)]
+impl OverlaySource {
+    pub fn new() -> OverlaySource {
+        OverlaySource::default()
+    }
+
+    // puts the source above all current layers
+    pub fn with_layer<S: ResourceSource + 'static>(mut self, source: S) -> OverlaySource {
+        self.push_layer(source);
+        self
+    }
+
+    pub fn push_layer<S: ResourceSource + 'static>(&mut self, source: S) {
+        self.layers.push(Box::new(source));
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+}
+
+impl ResourceSource for OverlaySource {
+    fn read(&self, name: &str) -> Result<Vec<u8>, Error> {
+        for layer in self.layers.iter().rev() {
+            match layer.read(name) {
+                Err(Error::NotFound { .. }) => continue,
+                // a layer that has the resource but fails to read it does not fall through,
+                // that would silently use the version the layer was meant to replace
+                result => return result,
+            }
+        }
+        Err(Error::NotFound {
+            name: name.into(),
+            path: self.path(name),
+        })
+    }
+
+    fn open(&self, name: &str) -> Result<Box<dyn ReadSeek>, Error> {
+        for layer in self.layers.iter().rev() {
+            match layer.open(name) {
+                Err(Error::NotFound { .. }) => continue,
+                result => return result,
+            }
+        }
+        Err(Error::NotFound {
+            name: name.into(),
+            path: self.path(name),
+        })
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.layers.iter().any(|layer| layer.contains(name))
+    }
+
+    // the layer the resource is read from, or the topmost one if no layer has it
+    fn path(&self, name: &str) -> PathBuf {
+        self.layers
+            .iter()
+            .rev()
+            .find(|layer| layer.contains(name))
+            .or_else(|| self.layers.last())
+            .map(|layer| layer.path(name))
+            .unwrap_or_else(|| PathBuf::from("<empty overlay>").join(name))
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::pack_format::{self, Entry};
+use super::{Error, ResourceSource};
+
+// resources in a pack written by the asset-pack tool or build.rs
+//
+// every resource is checked against the hash in the manifest when it is read
+pub struct PackSource {
+    path: PathBuf,
+    file: Mutex<File>,
+    // offset of the data of the first entry in the file
+    data_start: u64,
+    // entries reaching past it are corrupt
+    file_len: u64,
+    entries: HashMap<String, Entry>,
+}
+
+#[allow(dead_code)]
+impl PackSource {
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<PackSource, Error> {
+        let path = path.into();
+        let open = || -> io::Result<_> {
+            let mut reader = BufReader::new(File::open(&path)?);
+            let (manifest, data_start) = pack_format::read_header(&mut reader)?;
+            let file = reader.into_inner();
+            let file_len = file.metadata()?.len();
+            Ok((file, manifest, data_start, file_len))
+        };
+        let (file, manifest, data_start, file_len) = open().map_err(|e| Error::OpenArchive {
+            path: path.clone(),
+            inner: e,
+        })?;
+
+        Ok(PackSource {
+            path,
+            file: Mutex::new(file),
+            data_start,
+            file_len,
+            entries: manifest
+                .entries
+                .into_iter()
+                .map(|entry| (entry.name.clone(), entry))
+                .collect(),
+        })
+    }
+
+    pub fn pack_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.values()
+    }
+
+    // reads every resource once, for checking a whole pack up front
+    pub fn verify(&self) -> Result<(), Error> {
+        for name in self.entries.keys() {
+            self.read(name)?;
+        }
+        Ok(())
+    }
+}
+
+impl ResourceSource for PackSource {
+    fn read(&self, name: &str) -> Result<Vec<u8>, Error> {
+        let path = ResourceSource::path(self, name);
+        let entry = self.entries.get(name).ok_or_else(|| Error::NotFound {
+            name: name.into(),
+            path: path.clone(),
+        })?;
+
+        // the sizes come from the manifest, check them before allocating anything
+        let end = self
+            .data_start
+            .checked_add(entry.offset)
+            .and_then(|start| start.checked_add(entry.packed_size));
+        let in_file = matches!(end, Some(end) if end <= self.file_len);
+        if !in_file || !pack_format::sizes_are_plausible(entry) {
+            return Err(Error::Corrupt {
+                name: name.into(),
+                path,
+            });
+        }
+
+        let data = {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(self.data_start + entry.offset))
+                .and_then(|_| {
+                    let mut packed = Vec::with_capacity(entry.packed_size as usize);
+                    (&mut *file)
+                        .take(entry.packed_size)
+                        .read_to_end(&mut packed)?;
+                    Ok(packed)
+                })
+        }
+        .and_then(|packed| pack_format::unpack(entry, &packed))
+        .map_err(|e| Error::Io {
+            name: name.into(),
+            path: path.clone(),
+            inner: e,
+        })?;
+
+        if data.len() as u64 != entry.size || pack_format::hash(&data) != entry.hash {
+            return Err(Error::Corrupt {
+                name: name.into(),
+                path,
+            });
+        }
+        Ok(data)
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::process;
+
+    use super::*;
+    use crate::resources::pack_format::{Compression, PackWriter};
+
+    // a pack of the entries and their packed data in the temporary directory
+    fn write_pack(test: &str, entries: Vec<(Entry, &[u8])>) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("opengl-globe-{}-{}.pack", test, process::id()));
+        let mut writer = PackWriter::new();
+        for (entry, packed) in entries {
+            writer.add_packed(entry, packed.to_vec());
+        }
+        writer.write(&mut File::create(&path).unwrap()).unwrap();
+        path
+    }
+
+    fn entry(name: &str, packed: &[u8], size: u64, compression: Compression) -> Entry {
+        Entry {
+            name: name.into(),
+            offset: 0,
+            packed_size: packed.len() as u64,
+            size,
+            hash: pack_format::hash(packed),
+            compression,
+        }
+    }
+
+    #[test]
+    fn entries_are_read_and_checked() {
+        let path = write_pack(
+            "read",
+            vec![
+                (entry("a.txt", b"a", 1, Compression::None), b"a"),
+                (entry("b.txt", b"b", 1, Compression::None), b"b"),
+            ],
+        );
+        let pack = PackSource::open(&path).unwrap();
+        assert_eq!(pack.read("b.txt").unwrap(), b"b");
+        assert!(pack.verify().is_ok());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn changed_data_is_corrupt() {
+        let path = write_pack(
+            "changed",
+            vec![(entry("a.txt", b"hello", 5, Compression::None), b"hello")],
+        );
+        let position = {
+            let pack = PackSource::open(&path).unwrap();
+            pack.data_start + pack.entries["a.txt"].offset + 1
+        };
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[position as usize] ^= 1;
+        fs::write(&path, bytes).unwrap();
+
+        let pack = PackSource::open(&path).unwrap();
+        match pack.read("a.txt") {
+            Err(Error::Corrupt { name, .. }) => assert_eq!(name, "a.txt"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(pack.verify().is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn impossible_sizes_are_corrupt() {
+        let data = b"data";
+        let truncated = Entry {
+            packed_size: 1 << 40,
+            ..entry("truncated", data, 4, Compression::None)
+        };
+        let cases = vec![
+            (
+                entry("deflated", data, u64::MAX, Compression::Deflate),
+                &data[..],
+            ),
+            (entry("far_away", data, 4, Compression::None), &data[..]),
+            (entry("stored", data, 1 << 40, Compression::None), &data[..]),
+            // last, so it does not move the others out of the file
+            (truncated, &data[..]),
+        ];
+        let path = write_pack("sizes", cases);
+
+        // the writer recomputes offsets, move one of them past anything a pack could hold
+        let mut pack = PackSource::open(&path).unwrap();
+        pack.entries.get_mut("far_away").unwrap().offset = u64::MAX;
+
+        for name in &["deflated", "far_away", "stored", "truncated"] {
+            match pack.read(name) {
+                Err(Error::Corrupt { name: corrupt, .. }) => assert_eq!(&corrupt, name),
+                other => panic!("unexpected {:?} for {}", other.map(|data| data.len()), name),
+            }
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,533 @@
+// the asset pack format, shared with the asset-pack tool and build.rs through #[path]
+//
+// a pack starts with MAGIC, the format version as u32 and the length of the manifest as u64,
+// both little endian, followed by the manifest as JSON and then the data of every entry
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use flate2::read::{DeflateDecoder, DeflateEncoder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const MAGIC: &[u8; 8] = b"GLBPACK\0";
+pub const VERSION: u32 = 1;
+
+// in the root of an assets directory, lists patterns of files that are not packed
+#[allow(dead_code)]
+pub const IGNORE_FILE: &str = ".assetignore";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    Deflate,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    // from the end of the manifest
+    pub offset: u64,
+    // as stored in the pack
+    pub packed_size: u64,
+    // of the resource itself
+    pub size: u64,
+    // sha-256 of the resource as lowercase hex
+    pub hash: String,
+    pub compression: Compression,
+}
+
+// sorted by name
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub entries: Vec<Entry>,
+}
+
+pub fn hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// leaves reader at the start of the data and returns the manifest and the offset of the data
+//
+// the asset-pack tool only writes packs
+#[allow(dead_code)]
+pub fn read_header<R: Read>(reader: &mut R) -> io::Result<(Manifest, u64)> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not an asset pack"));
+    }
+
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(invalid_data(format!(
+            "unsupported pack version {}, expected {}",
+            version, VERSION
+        )));
+    }
+
+    let mut manifest_len = [0; 8];
+    reader.read_exact(&mut manifest_len)?;
+    let manifest_len = u64::from_le_bytes(manifest_len);
+
+    let mut manifest = Vec::new();
+    reader.take(manifest_len).read_to_end(&mut manifest)?;
+    if manifest.len() as u64 != manifest_len {
+        return Err(invalid_data("the manifest is truncated"));
+    }
+    let manifest = serde_json::from_slice(&manifest).map_err(invalid_data)?;
+
+    Ok((manifest, (MAGIC.len() + 4 + 8) as u64 + manifest_len))
+}
+
+// deflate can not make data smaller than this, a larger ratio is a corrupt manifest
+#[allow(dead_code)]
+const MAX_DEFLATE_RATIO: u64 = 1032;
+
+// false if no data could have the sizes of entry, so nothing is allocated for a size that
+// comes from a corrupt or malicious manifest
+#[allow(dead_code)]
+pub fn sizes_are_plausible(entry: &Entry) -> bool {
+    match entry.compression {
+        Compression::None => entry.size == entry.packed_size,
+        Compression::Deflate => entry.size <= entry.packed_size.saturating_mul(MAX_DEFLATE_RATIO),
+    }
+}
+
+// the data is not checked against the size and hash of the entry, only never inflated past
+// the size
+#[allow(dead_code)]
+pub fn unpack(entry: &Entry, packed: &[u8]) -> io::Result<Vec<u8>> {
+    if packed.len() as u64 != entry.packed_size || !sizes_are_plausible(entry) {
+        return Err(invalid_data(format!(
+            "the sizes of {} in the manifest are impossible",
+            entry.name
+        )));
+    }
+
+    match entry.compression {
+        Compression::None => Ok(packed.to_vec()),
+        Compression::Deflate => {
+            let mut data = Vec::with_capacity(entry.size as usize);
+            DeflateDecoder::new(packed)
+                .take(entry.size)
+                .read_to_end(&mut data)?;
+            Ok(data)
+        }
+    }
+}
+
+// collects resources in memory until the whole pack is written, the game itself only reads
+// packs
+#[allow(dead_code)]
+#[derive(Default)]
+pub struct PackWriter {
+    entries: Vec<(Entry, Vec<u8>)>,
+}
+
+#[allow(dead_code)]
+impl PackWriter {
+    pub fn new() -> PackWriter {
+        PackWriter::default()
+    }
+
+    // deflate is only kept for resources it makes smaller, already compressed images usually
+    // end up stored
+    pub fn add(&mut self, name: &str, data: &[u8], compression: Compression) -> io::Result<&Entry> {
+        let (compression, packed) = match compression {
+            Compression::None => (Compression::None, data.to_vec()),
+            Compression::Deflate => {
+                let mut packed = Vec::new();
+                DeflateEncoder::new(data, flate2::Compression::best()).read_to_end(&mut packed)?;
+                if packed.len() < data.len() {
+                    (Compression::Deflate, packed)
+                } else {
+                    (Compression::None, data.to_vec())
+                }
+            }
+        };
+
+        let entry = Entry {
+            name: name.into(),
+            offset: 0,
+            packed_size: packed.len() as u64,
+            size: data.len() as u64,
+            hash: hash(data),
+            compression,
+        };
+        Ok(self.add_packed(entry, packed))
+    }
+
+    // data that is already packed, like an unchanged entry of an older pack, the offset of
+    // entry is ignored
+    pub fn add_packed(&mut self, entry: Entry, packed: Vec<u8>) -> &Entry {
+        self.entries
+            .retain(|(existing, _)| existing.name != entry.name);
+        self.entries.push((entry, packed));
+        &self.entries.last().unwrap().0
+    }
+
+    // the manifest the pack will be written with
+    pub fn manifest(&mut self) -> Manifest {
+        self.entries.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+
+        let mut offset = 0;
+        for (entry, _) in &mut self.entries {
+            entry.offset = offset;
+            offset += entry.packed_size;
+        }
+        Manifest {
+            entries: self
+                .entries
+                .iter()
+                .map(|(entry, _)| entry.clone())
+                .collect(),
+        }
+    }
+
+    pub fn write<W: Write>(mut self, out: &mut W) -> io::Result<Manifest> {
+        let manifest = self.manifest();
+        let manifest_json = serde_json::to_vec(&manifest).map_err(invalid_data)?;
+
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(manifest_json.len() as u64).to_le_bytes())?;
+        out.write_all(&manifest_json)?;
+        for (_, packed) in &self.entries {
+            out.write_all(packed)?;
+        }
+        out.flush()?;
+
+        Ok(manifest)
+    }
+}
+
+// the patterns of an ignore file, one per line, blank lines and lines starting with # are skipped
+//
+// like .gitignore, * matches any run of characters and ? a single one, neither matches a /,
+// ** matches across directories, so **/ is any number of directories and a trailing /** all
+// files below one
+//
+// a pattern ending in / only matches directories, a pattern that contains any other / is matched
+// against the resource name from the root, others against every directory and the file name,
+// a file inside an ignored directory is ignored
+//
+// a pattern starting with ! includes what earlier patterns ignored, the last matching pattern
+// decides. Like in git it can not include a file inside an ignored directory again, `*` followed
+// by `!*/` keeps the directories so that later patterns can include files in them
+//
+// only build.rs and the asset-pack tool decide what goes into a pack
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default)]
+pub struct IgnorePatterns {
+    patterns: Vec<IgnorePattern>,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+struct IgnorePattern {
+    glob: String,
+    negated: bool,
+    directories_only: bool,
+    // matched against whole paths instead of single names
+    anchored: bool,
+}
+
+#[allow(dead_code)]
+impl IgnorePatterns {
+    pub fn parse(text: &str) -> IgnorePatterns {
+        IgnorePatterns {
+            patterns: text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(IgnorePattern::parse)
+                .collect(),
+        }
+    }
+
+    // IGNORE_FILE in dir, no patterns if there is none
+    pub fn from_dir(dir: &Path) -> io::Result<IgnorePatterns> {
+        match fs::read_to_string(dir.join(IGNORE_FILE)) {
+            Ok(text) => Ok(IgnorePatterns::parse(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(IgnorePatterns::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    // the ignore file itself is always ignored
+    pub fn is_ignored(&self, name: &str) -> bool {
+        // name is a file, so every / in it ends a directory
+        let directories = name.match_indices('/').map(|(end, _)| (&name[..end], true));
+        name == IGNORE_FILE
+            || directories
+                .chain(std::iter::once((name, false)))
+                .any(|(path, is_directory)| self.excludes(path, is_directory))
+    }
+
+    // the last pattern matching path itself decides, without looking at its directories
+    fn excludes(&self, path: &str, is_directory: bool) -> bool {
+        let last_match = self
+            .patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(path, is_directory));
+        matches!(last_match, Some(pattern) if !pattern.negated)
+    }
+}
+
+#[allow(dead_code)]
+impl IgnorePattern {
+    fn parse(line: &str) -> IgnorePattern {
+        let (negated, glob) = match line.strip_prefix('!') {
+            Some(glob) => (true, glob),
+            None => (false, line),
+        };
+        let (directories_only, glob) = match glob.strip_suffix('/') {
+            Some(glob) => (true, glob),
+            None => (false, glob),
+        };
+        let anchored = glob.contains('/');
+        IgnorePattern {
+            glob: glob.trim_start_matches('/').into(),
+            negated,
+            directories_only,
+            anchored,
+        }
+    }
+
+    fn matches(&self, path: &str, is_directory: bool) -> bool {
+        if self.directories_only && !is_directory {
+            return false;
+        }
+        let target = if self.anchored {
+            path
+        } else {
+            path.rsplit('/').next().unwrap_or(path)
+        };
+        glob_match(self.glob.as_bytes(), target.as_bytes())
+    }
+}
+
+#[allow(dead_code)]
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        // no directory at all, or everything up to any /
+        [b'*', b'*', b'/', rest @ ..] => {
+            glob_match(rest, text)
+                || (0..text.len())
+                    .filter(|&index| text[index] == b'/')
+                    .any(|index| glob_match(rest, &text[index + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|index| glob_match(rest, &text[index..])),
+        [b'*', rest @ ..] => {
+            // try every split, stopping at the first / the star cannot cross
+            (0..=text.len())
+                .take_while(|&index| index == 0 || text[index - 1] != b'/')
+                .any(|index| glob_match(rest, &text[index..]))
+        }
+        [c, rest @ ..] => match text.split_first() {
+            Some((&t, text_rest)) if (*c == b'?' && t != b'/') || *c == t => {
+                glob_match(rest, text_rest)
+            }
+            _ => false,
+        },
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_round_trip() {
+        let text = "void main() {}\n".repeat(64);
+        // xorshift, deflate can not shrink it
+        let mut state = 0x1234_5678u32;
+        let noise: Vec<u8> = (0..512)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+
+        let mut writer = PackWriter::new();
+        writer
+            .add("shaders/globe.vert", text.as_bytes(), Compression::Deflate)
+            .unwrap();
+        writer.add("empty.txt", b"", Compression::Deflate).unwrap();
+        writer
+            .add("noise.bin", &noise, Compression::Deflate)
+            .unwrap();
+        writer
+            .add("stored.txt", text.as_bytes(), Compression::None)
+            .unwrap();
+        let mut pack = Vec::new();
+        let written = writer.write(&mut pack).unwrap();
+
+        let mut reader = io::Cursor::new(&pack[..]);
+        let (manifest, data_start) = read_header(&mut reader).unwrap();
+        assert_eq!(manifest, written);
+        assert_eq!(reader.position(), data_start);
+
+        let names: Vec<_> = manifest.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["empty.txt", "noise.bin", "shaders/globe.vert", "stored.txt"]
+        );
+        let compressions: Vec<_> = manifest.entries.iter().map(|e| e.compression).collect();
+        assert_eq!(
+            compressions,
+            [
+                Compression::None,
+                Compression::None,
+                Compression::Deflate,
+                Compression::None
+            ]
+        );
+
+        let expected: [&[u8]; 4] = [b"", &noise, text.as_bytes(), text.as_bytes()];
+        for (entry, expected) in manifest.entries.iter().zip(&expected) {
+            let start = (data_start + entry.offset) as usize;
+            let packed = &pack[start..start + entry.packed_size as usize];
+            let data = unpack(entry, packed).unwrap();
+            assert_eq!(&data[..], *expected, "{}", entry.name);
+            assert_eq!(hash(&data), entry.hash);
+        }
+    }
+
+    #[test]
+    fn unpack_rejects_impossible_sizes() {
+        let mut writer = PackWriter::new();
+        let entry = writer
+            .add("a.txt", &[b'a'; 4096], Compression::Deflate)
+            .unwrap()
+            .clone();
+        let mut packed = Vec::new();
+        writer.write(&mut packed).unwrap();
+        let packed = &packed[packed.len() - entry.packed_size as usize..];
+
+        let oversized = Entry {
+            size: u64::MAX,
+            ..entry.clone()
+        };
+        let err = unpack(&oversized, packed).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // a size smaller than the data only ever inflates that much
+        let undersized = Entry { size: 16, ..entry };
+        assert_eq!(unpack(&undersized, packed).unwrap(), &[b'a'; 16][..]);
+    }
+
+    fn ignored(patterns: &str, names: &[&str]) -> Vec<bool> {
+        let patterns = IgnorePatterns::parse(patterns);
+        names.iter().map(|name| patterns.is_ignored(name)).collect()
+    }
+
+    #[test]
+    fn single_star_and_question_mark_stay_in_one_name() {
+        assert_eq!(
+            ignored(
+                "# sources\n*.blend\n\ntexture?.png\n",
+                &[
+                    "ship.blend",
+                    "models/ship.blend",
+                    "ship.blend1",
+                    "texture1.png",
+                    "textures/texture1.png",
+                    "texture10.png",
+                ]
+            ),
+            [true, true, false, true, true, false]
+        );
+        assert_eq!(
+            ignored("models/*.obj", &["models/cube.obj", "models/old/cube.obj"]),
+            [true, false]
+        );
+        assert_eq!(ignored("*", &["a.txt", "shaders/a.vert"]), [true, true]);
+    }
+
+    #[test]
+    fn double_star_crosses_directories() {
+        let names = [
+            "debug.frag",
+            "shaders/debug.frag",
+            "shaders/a/b/debug.frag",
+            "shaders/globe.frag",
+            "docs/readme.md",
+            "docs/images/globe.png",
+            "documents.txt",
+        ];
+        assert_eq!(
+            ignored("shaders/**/debug.frag", &names),
+            [false, true, true, false, false, false, false]
+        );
+        assert_eq!(
+            ignored("**/debug.frag", &names),
+            [true, true, true, false, false, false, false]
+        );
+        assert_eq!(
+            ignored("docs/**", &names),
+            [false, false, false, false, true, true, false]
+        );
+        assert_eq!(
+            ignored("**/*.png", &names),
+            [false, false, false, false, false, true, false]
+        );
+    }
+
+    #[test]
+    fn negation_includes_again_and_the_last_match_wins() {
+        let names = ["a.png", "ui/keep.png", "keep.txt"];
+        assert_eq!(ignored("*.png\n!keep.png", &names), [true, false, false]);
+        assert_eq!(ignored("!keep.png\n*.png", &names), [true, true, false]);
+        assert_eq!(ignored("*\n!*.png", &names), [false, true, true]);
+        assert_eq!(ignored("*\n!*/\n!*.png", &names), [false, false, true]);
+    }
+
+    #[test]
+    fn negation_can_not_include_files_of_ignored_directories() {
+        let names = ["docs/readme.md", "docs/keep.md", "docs/images/keep.md"];
+        assert_eq!(ignored("docs/\n!docs/keep.md", &names), [true, true, true]);
+        assert_eq!(ignored("docs\n!keep.md", &names), [true, true, true]);
+        // docs/** ignores what is inside, not docs itself
+        assert_eq!(
+            ignored("docs/**\n!docs/keep.md", &names),
+            [true, false, true]
+        );
+    }
+
+    #[test]
+    fn directory_patterns_only_match_directories() {
+        let names = [
+            "build/a.txt",
+            "models/build/a.txt",
+            "build",
+            "raw/scan.png",
+            "models/raw/scan.png",
+        ];
+        assert_eq!(ignored("build/", &names), [true, true, false, false, false]);
+        assert_eq!(ignored("/raw/", &names), [false, false, false, true, false]);
+        assert_eq!(ignored("raw", &names), [false, false, false, true, true]);
+    }
+
+    #[test]
+    fn the_ignore_file_is_always_ignored() {
+        assert!(IgnorePatterns::default().is_ignored(IGNORE_FILE));
+        assert!(IgnorePatterns::parse("!.assetignore").is_ignored(IGNORE_FILE));
+    }
+}
@@ -0,0 +1,82 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use super::Error;
+
+// when set, the resource directory or archive is taken from here and nowhere else
+pub const ASSETS_DIR_VAR: &str = "ASSETS_DIR";
+
+// candidate resource directories or archives, the first one that exists is used
+#[derive(Clone, Debug, Default)]
+pub struct SearchPaths {
+    paths: Vec<PathBuf>,
+}
+
+#[allow(dead_code)]
+impl SearchPaths {
+    pub fn new() -> SearchPaths {
+        SearchPaths::default()
+    }
+
+    // $ASSETS_DIR if set, otherwise rel_path next to the executable, rel_path in the current
+    // directory and, in debug builds, the assets/ directory of this crate for `cargo test` and
+    // `cargo run` from anywhere
+    pub fn standard(rel_path: &Path) -> SearchPaths {
+        if let Some(dir) = env::var_os(ASSETS_DIR_VAR) {
+            return SearchPaths::new().with(dir);
+        }
+
+        let mut paths = SearchPaths::new();
+        if let Some(exe_dir) = env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        {
+            paths.push(exe_dir.join(rel_path));
+        }
+        if let Ok(current_dir) = env::current_dir() {
+            paths.push(current_dir.join(rel_path));
+        }
+        if cfg!(debug_assertions) {
+            paths.push(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        }
+        paths
+    }
+
+    pub fn with<P: Into<PathBuf>>(mut self, path: P) -> SearchPaths {
+        self.push(path);
+        self
+    }
+
+    // paths that are already in the list are not tried twice
+    pub fn push<P: Into<PathBuf>>(&mut self, path: P) {
+        let path = path.into();
+        if !self.paths.contains(&path) {
+            self.paths.push(path);
+        }
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    pub fn find(&self) -> Result<&Path, Error> {
+        self.paths
+            .iter()
+            .find(|path| path.exists())
+            .map(PathBuf::as_path)
+            .ok_or_else(|| Error::NoResourceDir {
+                tried: self.paths.clone(),
+            })
+    }
+}
+
+pub fn display_paths(paths: &[PathBuf]) -> String {
+    if paths.is_empty() {
+        return "no paths".into();
+    }
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
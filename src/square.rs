@@ -1,19 +1,23 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
 use anyhow::Result;
 
-use gl_render_derive::VertexAttribPointers;
+use nalgebra_glm as glm;
+
+use gl_render_derive::{ProgramUniforms, VertexAttribPointers};
 
+use crate::assets::{AssetServer, Handle, LoadState};
 use crate::buffer::{ArrayBuffer, ElementArrayBuffer, VertexArray};
-use crate::gl_render::{self, data, texture, Program};
-use crate::resources::Resources;
+use crate::gl_render::texture::Texture;
+use crate::gl_render::uniform::Sampler2D;
+use crate::gl_render::{data, Program, Uniforms};
 
 #[derive(Copy, Clone, Debug, VertexAttribPointers)]
 #[repr(C, packed)]
 pub struct Vertex {
-    #[location = 0]
     pos: data::vec3,
-    #[location = 1]
     color: data::U2U10U10U10RevFloat,
-    #[location = 2]
     texcoord: data::vec2,
 }
 
@@ -27,19 +31,39 @@ impl Vertex {
     }
 }
 
-pub struct Square {
-    program: gl_render::Program,
+#[derive(ProgramUniforms)]
+pub struct SquareUniforms {
+    pub model: glm::Mat4,
+    pub view: glm::Mat4,
+    pub projection: glm::Mat4,
+    pub container: Sampler2D,
+    pub face: Sampler2D,
+}
+
+// the buffers are the same for every square
+struct SquareVertices {
     _vbo: ArrayBuffer,
-    ebo: ElementArrayBuffer,
+    _ebo: ElementArrayBuffer,
     vao: VertexArray,
-    container_tex: texture::Texture,
-    face_tex: texture::Texture,
 }
 
-impl Square {
-    pub fn new(res: &Resources, gl: &gl::Gl) -> Result<Square> {
-        let program = Program::from_res(gl, &res, "shaders/square")?;
+thread_local! {
+    static SQUARE_VERTICES: RefCell<Weak<SquareVertices>> = const { RefCell::new(Weak::new()) };
+}
 
+impl SquareVertices {
+    fn shared(gl: &gl::Gl) -> Rc<SquareVertices> {
+        SQUARE_VERTICES.with(|shared| {
+            if let Some(vertices) = shared.borrow().upgrade() {
+                return vertices;
+            }
+            let vertices = Rc::new(SquareVertices::new(gl));
+            *shared.borrow_mut() = Rc::downgrade(&vertices);
+            vertices
+        })
+    }
+
+    fn new(gl: &gl::Gl) -> SquareVertices {
         // vertex data
         let vertices: Vec<Vertex> = vec![
             Vertex::new((-0.5, -0.5, 0.0), (1.0, 0.0, 0.0, 1.0), (0.0, 0.0)), // bottom left
@@ -51,46 +75,101 @@ impl Square {
         // index buffer data
         let indices: Vec<gl::types::GLuint> = vec![0, 1, 2, 2, 1, 3];
 
-        // load texture
-        program.set_used();
-        let container_tex = texture::Texture::new(gl, res, "textures/container.jpg", &program, "container")?;
-        let face_tex = texture::Texture::new(gl, res, "textures/awesomeface.png", &program, "face")?;
-        program.set_unused();
+        let vbo = ArrayBuffer::from_data(gl, &vertices);
+        let ebo = ElementArrayBuffer::from_data(gl, &indices);
 
-        let vbo = ArrayBuffer::new(gl);
-        vbo.bind();
-        vbo.static_draw_data(&vertices);
-        vbo.unbind();
+        let vao = VertexArray::new(gl);
+        Vertex::vertex_format(&vao, 0);
+        vao.vertex_buffer(0, &vbo, 0, std::mem::size_of::<Vertex>());
+        vao.element_buffer(&ebo);
 
-        let ebo = ElementArrayBuffer::new(gl);
-        ebo.bind();
-        ebo.static_draw_data(&indices);
-        ebo.unbind();
+        SquareVertices {
+            _vbo: vbo,
+            _ebo: ebo,
+            vao,
+        }
+    }
+}
 
-        let vao = VertexArray::new(gl);
+pub struct Square {
+    program: Handle<Program>,
+    // resolved against the program the handle held when they were last applied
+    uniforms: RefCell<Uniforms<SquareUniforms>>,
+    vertices: Rc<SquareVertices>,
+    container_tex: Handle<Texture>,
+    face_tex: Handle<Texture>,
+}
 
-        vao.bind();
-        vbo.bind();
-        Vertex::vertex_attrib_pointers(gl);
-        vbo.unbind();
-        vao.unbind();
+impl Square {
+    // shaders/square.vert uses #pragma vertex_inputs(Vertex), the asset server has to be
+    // created with a preprocessor that knows Vertex::GLSL_INPUTS
+    pub fn new(assets: &AssetServer, gl: &gl::Gl) -> Result<Square> {
+        let program = assets.load::<Program>("shaders/square")?;
+        let container_tex = assets.load::<Texture>("textures/container.jpg")?;
+        let face_tex = assets.load::<Texture>("textures/awesomeface.png")?;
+
+        let uniforms = {
+            // loading returned without an error, so all of them are there
+            let values = SquareUniforms {
+                model: glm::Mat4::identity(),
+                view: glm::Mat4::identity(),
+                projection: glm::Mat4::identity(),
+                container: container_tex.get().unwrap().sampler(),
+                face: face_tex.get().unwrap().sampler(),
+            };
+            let is_placeholder = program.load_state() == LoadState::Fallback;
+            let program = program.get().unwrap();
+            if is_placeholder {
+                // the placeholder only reads the position and has no samplers
+                Uniforms::new_partial(&program, values)
+            } else {
+                program.check_vertex_layout(Vertex::LAYOUT)?;
+                Uniforms::new(&program, values)?
+            }
+        };
 
         Ok(Square {
             program,
-            _vbo: vbo,
-            ebo,
-            vao,
+            uniforms: RefCell::new(uniforms),
+            vertices: SquareVertices::shared(gl),
             container_tex,
             face_tex,
         })
     }
 
     pub fn render(&self, gl: &gl::Gl) {
-        self.program.set_used();
-        self.vao.bind();
-        self.ebo.bind();
-        self.container_tex.bind();
-        self.face_tex.bind();
+        let (program, container_tex, face_tex) = match (
+            self.program.get(),
+            self.container_tex.get(),
+            self.face_tex.get(),
+        ) {
+            (Some(program), Some(container_tex), Some(face_tex)) => {
+                (program, container_tex, face_tex)
+            }
+            _ => return,
+        };
+
+        let mut uniforms = self.uniforms.borrow_mut();
+        if uniforms.program() != program.id() {
+            // a reload replaced the program, or the placeholder with the real one
+            if self.program.load_state() == LoadState::Fallback {
+                uniforms.rebind_partial(&program);
+            } else if let Err(err) = program
+                .check_vertex_layout(Vertex::LAYOUT)
+                .and_then(|()| uniforms.rebind(&program))
+            {
+                eprintln!("warning: shaders/square no longer fits the square: {}", err);
+                uniforms.rebind_partial(&program);
+            }
+        }
+        // a reloaded texture comes with another unit
+        uniforms.container = container_tex.sampler();
+        uniforms.face = face_tex.sampler();
+        uniforms.apply(&program);
+        program.set_used();
+        self.vertices.vao.bind();
+        container_tex.bind();
+        face_tex.bind();
 
         unsafe {
             gl.DrawElements(
@@ -101,14 +180,17 @@ impl Square {
             );
         }
 
-        self.face_tex.unbind();
-        self.container_tex.unbind();
-        self.ebo.unbind();
-        self.vao.unbind();
-        self.program.set_unused();
+        face_tex.unbind();
+        container_tex.unbind();
+        self.vertices.vao.unbind();
+        program.set_unused();
     }
 
-    pub fn program(&self) -> &Program {
+    pub fn program(&self) -> &Handle<Program> {
         &self.program
     }
+
+    pub fn uniforms_mut(&mut self) -> &mut SquareUniforms {
+        self.uniforms.get_mut()
+    }
 }